@@ -0,0 +1,219 @@
+use std::mem;
+use std::sync::Mutex;
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::http::HttpState;
+use crate::lockfile::LockfileState;
+use crate::riot::remote::{RemoteClient, RemoteState};
+
+/// Where the local player is in the game lifecycle.
+/// Emitted to the webview as part of `game-phase`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum GamePhase {
+    /// Nothing is known yet, or the game restarted.
+    Unknown,
+    Menus,
+    /// Agent select. The match ID is none until it has been looked up.
+    Pregame { match_id: Option<String> },
+    InGame { match_id: Option<String> },
+    /// Back in the menus after a match, until the next one starts.
+    PostGame { match_id: Option<String> }
+}
+
+impl Default for GamePhase {
+    fn default() -> Self {
+        GamePhase::Unknown
+    }
+}
+
+/// Something which may move the game lifecycle forward.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// The local player's presence `sessionLoopState` changed.
+    SessionLoopState(String),
+    /// The player is in the pre-game with this ID, from a lookup or the game log.
+    Pregame(String),
+    /// The player is in the core-game with this ID, from a lookup or the game log.
+    CoreGame(String),
+    /// The game restarted.
+    Reset
+}
+
+/// Emitted as `game-phase` when the phase changes.
+#[derive(Clone, Debug, Serialize)]
+pub struct GamePhaseChanged {
+    pub previous: GamePhase,
+    pub current: GamePhase
+}
+
+/// The current game phase, kept in Tauri managed state.
+#[derive(Default)]
+pub struct GameState(pub Mutex<GamePhase>);
+
+/// Returns the phase which follows `phase` after a signal.
+pub fn transition(phase: &GamePhase, signal: &Signal) -> GamePhase {
+    match signal {
+        Signal::Reset => GamePhase::Unknown,
+        Signal::SessionLoopState(state) => match (state.as_str(), phase) {
+            ("MENUS", GamePhase::InGame { match_id }) | ("MENUS", GamePhase::PostGame { match_id }) =>
+                GamePhase::PostGame { match_id: match_id.clone() },
+            ("MENUS", _) => GamePhase::Menus,
+            ("PREGAME", GamePhase::Pregame { .. }) => phase.clone(),
+            ("PREGAME", _) => GamePhase::Pregame { match_id: None },
+            ("INGAME", GamePhase::InGame { .. }) => phase.clone(),
+            // A match keeps the ID of its pre-game.
+            ("INGAME", GamePhase::Pregame { match_id }) => GamePhase::InGame { match_id: match_id.clone() },
+            ("INGAME", _) => GamePhase::InGame { match_id: None },
+            _ => phase.clone()
+        },
+        Signal::Pregame(id) => match phase {
+            // Late lookups and log lines for a match which has already started.
+            GamePhase::InGame { match_id } | GamePhase::PostGame { match_id }
+                if match_id.as_deref() == Some(id.as_str()) => phase.clone(),
+            _ => GamePhase::Pregame { match_id: Some(id.clone()) }
+        },
+        Signal::CoreGame(id) => match phase {
+            GamePhase::PostGame { match_id } if match_id.as_deref() == Some(id.as_str()) => phase.clone(),
+            _ => GamePhase::InGame { match_id: Some(id.clone()) }
+        }
+    }
+}
+
+/// Applies a signal, emitting `game-phase` if the phase changed.
+/// Entering a pre-game or match without a known ID looks the ID up.
+pub fn apply(app: &AppHandle, signal: Signal) {
+    change(app, |phase| Some(transition(phase, &signal)));
+}
+
+/// Returns the phase after a looked-up signal.
+/// Returns none if the phase is no longer the one the lookup was made for.
+fn resolve_lookup(phase: &GamePhase, origin: &GamePhase, signal: &Signal) -> Option<GamePhase> {
+    if phase != origin {
+        return None;
+    }
+
+    Some(transition(phase, signal))
+}
+
+/// Moves to the phase returned by `next`, if any, emitting `game-phase` if it changed.
+fn change<F: FnOnce(&GamePhase) -> Option<GamePhase>>(app: &AppHandle, next: F) {
+    let state = app.state::<GameState>();
+    let (previous, current) = {
+        let mut phase = state.0.lock().unwrap();
+        let next = match next(&phase) {
+            Some(next) if next != *phase => next,
+            _ => return
+        };
+
+        (mem::replace(&mut *phase, next.clone()), next)
+    };
+
+    info!("Game phase changed from {:?} to {:?}.", previous, current);
+    if let Err(error) = app.emit_all("game-phase", GamePhaseChanged { previous, current: current.clone() }) {
+        warn!("Unable to emit 'game-phase': {}", error);
+    }
+
+    match current {
+        GamePhase::Pregame { match_id: None } | GamePhase::InGame { match_id: None } =>
+            look_up(app.clone(), current),
+        _ => {}
+    }
+}
+
+/// Looks up the ID of the pre-game or match the player is in.
+/// The result is dropped if the phase has changed since `origin`.
+fn look_up(app: AppHandle, origin: GamePhase) {
+    tauri::async_runtime::spawn(async move {
+        let http = app.state::<HttpState>();
+        let remote = app.state::<RemoteState>();
+        let lockfile = app.state::<LockfileState>();
+        let client = RemoteClient::new(&http, &remote, &lockfile);
+
+        let puuid = match client.puuid() {
+            Ok(puuid) => puuid,
+            Err(_) => return
+        };
+
+        let signal = match origin {
+            GamePhase::Pregame { .. } => client.current_pregame(&puuid).await
+                .map(|pregame| Signal::Pregame(pregame.match_id)),
+            _ => client.current_game(&puuid).await
+                .map(|game| Signal::CoreGame(game.match_id))
+        };
+
+        match signal {
+            Ok(signal) => change(&app, |phase| resolve_lookup(phase, &origin, &signal)),
+            Err(error) => warn!("Unable to look up the current match: {}", error)
+        }
+    });
+}
+
+#[tauri::command]
+pub fn game_phase(state: State<'_, GameState>) -> GamePhase {
+    state.0.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a phase shorthand: `U`, `M`, or `P`, `I` and `S`
+    /// for pre-game, in-game and post-game, followed by the match ID if known.
+    fn phase(code: &str) -> GamePhase {
+        let match_id = Some(&code[1..]).filter(|id| !id.is_empty()).map(str::to_string);
+        match &code[..1] {
+            "U" => GamePhase::Unknown,
+            "M" => GamePhase::Menus,
+            "P" => GamePhase::Pregame { match_id },
+            "I" => GamePhase::InGame { match_id },
+            "S" => GamePhase::PostGame { match_id },
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn transition_covers_every_phase_and_signal() {
+        let state = |state: &str| Signal::SessionLoopState(state.to_string());
+        let signals = [
+            state("MENUS"), state("PREGAME"), state("INGAME"), state("REPLAY"),
+            Signal::Pregame("a".to_string()), Signal::Pregame("b".to_string()),
+            Signal::CoreGame("a".to_string()), Signal::CoreGame("b".to_string()),
+            Signal::Reset
+        ];
+
+        // Each row is a phase, followed by the phase after each signal above.
+        let table = [
+            ("U",  ["M",  "P",  "I",  "U",  "Pa", "Pb", "Ia", "Ib", "U"]),
+            ("M",  ["M",  "P",  "I",  "M",  "Pa", "Pb", "Ia", "Ib", "U"]),
+            ("P",  ["M",  "P",  "I",  "P",  "Pa", "Pb", "Ia", "Ib", "U"]),
+            ("Pa", ["M",  "Pa", "Ia", "Pa", "Pa", "Pb", "Ia", "Ib", "U"]),
+            ("I",  ["S",  "P",  "I",  "I",  "Pa", "Pb", "Ia", "Ib", "U"]),
+            ("Ia", ["Sa", "P",  "Ia", "Ia", "Ia", "Pb", "Ia", "Ib", "U"]),
+            ("S",  ["S",  "P",  "I",  "S",  "Pa", "Pb", "Ia", "Ib", "U"]),
+            ("Sa", ["Sa", "P",  "I",  "Sa", "Sa", "Pb", "Sa", "Ib", "U"])
+        ];
+
+        for (from, expected) in table {
+            for (signal, to) in signals.iter().zip(expected) {
+                assert_eq!(transition(&phase(from), signal), phase(to), "{} after {:?}", from, signal);
+            }
+        }
+    }
+
+    #[test]
+    fn stale_lookups_are_dropped() {
+        let pregame = Signal::Pregame("a".to_string());
+        assert_eq!(resolve_lookup(&phase("P"), &phase("P"), &pregame), Some(phase("Pa")));
+
+        // The match started while the pre-game was being looked up.
+        assert_eq!(resolve_lookup(&phase("I"), &phase("P"), &pregame), None);
+        // The game log found the ID first.
+        assert_eq!(resolve_lookup(&phase("Pb"), &phase("P"), &pregame), None);
+
+        let core_game = Signal::CoreGame("a".to_string());
+        assert_eq!(resolve_lookup(&phase("I"), &phase("I"), &core_game), Some(phase("Ia")));
+        assert_eq!(resolve_lookup(&phase("S"), &phase("I"), &core_game), None);
+    }
+}
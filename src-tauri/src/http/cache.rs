@@ -0,0 +1,293 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use base64::prelude::*;
+use log::warn;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::http::{HttpHeader, RawResponse};
+
+/// How long match history responses are kept.
+const MATCH_HISTORY_TTL: Duration = Duration::from_secs(60);
+
+/// How a route's responses may be cached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Responses are never cached.
+    None,
+    /// Responses are reused until they are older than the duration.
+    Ttl(Duration),
+    /// Responses never change once they exist.
+    Immutable
+}
+
+/// Picks the cache policy for a request.
+/// Only GET requests to known remote routes are cached;
+/// live game state (pregame, core-game) and the local API never are.
+pub fn policy(method: &str, url: &str) -> CachePolicy {
+    if !method.eq_ignore_ascii_case("GET") {
+        return CachePolicy::None;
+    }
+
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return CachePolicy::None
+    };
+
+    let path = url.path();
+    if path.starts_with("/match-details/v1/matches/") {
+        CachePolicy::Immutable
+    } else if path.starts_with("/match-history/v1/history/") {
+        CachePolicy::Ttl(MATCH_HISTORY_TTL)
+    } else {
+        CachePolicy::None
+    }
+}
+
+/// A cached response as stored on disk.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    method: String,
+    url: String,
+    /// Unix time in seconds the response was stored at.
+    stored_at: u64,
+    /// Unix time in seconds the response expires at, if ever.
+    expires_at: Option<u64>,
+    status: u16,
+    headers: Vec<HttpHeader>,
+    /// The response body, base64 encoded.
+    body: String
+}
+
+/// A summary of a cache entry, returned by `http_cache`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntryInfo {
+    pub method: String,
+    pub url: String,
+    pub stored_at: u64,
+    pub expires_at: Option<u64>,
+    pub size: usize
+}
+
+/// Stores responses on disk, one file per method and URL.
+#[derive(Default)]
+pub struct ResponseCache {
+    /// The directory entries are stored in.
+    /// Nothing is cached until this is set.
+    dir: Mutex<Option<PathBuf>>
+}
+
+impl ResponseCache {
+    /// Sets the directory entries are stored in.
+    pub fn set_directory(&self, dir: Option<PathBuf>) {
+        *self.dir.lock().unwrap() = dir;
+    }
+
+    /// Returns the path of the entry for a request.
+    fn path(&self, method: &str, url: &str) -> Option<PathBuf> {
+        let dir = self.dir.lock().unwrap().clone()?;
+        let key = Sha256::digest(format!("{} {}", method.to_uppercase(), url));
+        Some(dir.join(format!("{}.json", hex::encode(key))))
+    }
+
+    /// Returns the cached response for a request, if it is still fresh.
+    pub fn get(&self, method: &str, url: &str, policy: CachePolicy) -> Option<RawResponse> {
+        if policy == CachePolicy::None {
+            return None;
+        }
+
+        let path = self.path(method, url)?;
+        let entry = read_entry(&path)?;
+        if entry.expires_at.map_or(false, |expires_at| expires_at <= now()) {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        let body = BASE64_STANDARD.decode(&entry.body).ok()?;
+        Some(RawResponse {
+            status: entry.status,
+            headers: entry.headers,
+            body
+        })
+    }
+
+    /// Stores a response if the policy allows it.
+    pub fn put(&self, method: &str, url: &str, policy: CachePolicy, response: &RawResponse) {
+        let expires_at = match policy {
+            CachePolicy::None => return,
+            CachePolicy::Ttl(ttl) => Some(now() + ttl.as_secs()),
+            CachePolicy::Immutable => None
+        };
+
+        let path = match self.path(method, url) {
+            Some(path) => path,
+            None => return
+        };
+
+        let entry = CacheEntry {
+            method: method.to_uppercase(),
+            url: url.to_string(),
+            stored_at: now(),
+            expires_at,
+            status: response.status,
+            headers: response.headers.clone(),
+            body: BASE64_STANDARD.encode(&response.body)
+        };
+
+        let result = path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, serde_json::to_vec(&entry).unwrap()));
+        if let Err(error) = result {
+            warn!("Failed to cache {}: {}", url, error);
+        }
+    }
+
+    /// Lists every entry in the cache, including expired ones.
+    pub fn entries(&self) -> Vec<CacheEntryInfo> {
+        self.files().iter()
+            .filter_map(|path| read_entry(path))
+            .map(|entry| CacheEntryInfo {
+                size: BASE64_STANDARD.decode(&entry.body).map_or(0, |body| body.len()),
+                method: entry.method,
+                url: entry.url,
+                stored_at: entry.stored_at,
+                expires_at: entry.expires_at
+            })
+            .collect()
+    }
+
+    /// Removes every entry from the cache.
+    /// Returns the number of entries removed.
+    pub fn clear(&self) -> usize {
+        self.files().iter()
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+
+    /// Returns the path of every entry file.
+    fn files(&self) -> Vec<PathBuf> {
+        let dir = match self.dir.lock().unwrap().clone() {
+            Some(dir) => dir,
+            None => return Vec::new()
+        };
+
+        match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |extension| extension == "json"))
+                .collect(),
+            Err(_) => Vec::new()
+        }
+    }
+}
+
+/// Reads a cache entry from disk.
+/// Unreadable entries are treated as missing.
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    let text = fs::read(path).ok()?;
+    serde_json::from_slice(&text).ok()
+}
+
+/// Returns the current Unix time in seconds.
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATCH_DETAILS: &str = "https://pd.na.a.pvp.net/match-details/v1/matches/match";
+    const MATCH_HISTORY: &str = "https://pd.na.a.pvp.net/match-history/v1/history/puuid?startIndex=0&endIndex=20";
+
+    /// Creates a cache in a new temporary directory.
+    fn cache(name: &str) -> (ResponseCache, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("guilded-stats-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let cache = ResponseCache::default();
+        cache.set_directory(Some(dir.clone()));
+        (cache, dir)
+    }
+
+    fn response(body: &str) -> RawResponse {
+        RawResponse {
+            status: 200,
+            headers: vec![HttpHeader {
+                name: "content-type".to_string(),
+                value: Some("application/json".to_string()),
+                raw: None
+            }],
+            body: body.as_bytes().to_vec()
+        }
+    }
+
+    #[test]
+    fn policy_matches_routes() {
+        assert_eq!(policy("GET", MATCH_DETAILS), CachePolicy::Immutable);
+        assert_eq!(policy("get", MATCH_DETAILS), CachePolicy::Immutable);
+        assert_eq!(policy("GET", MATCH_HISTORY), CachePolicy::Ttl(MATCH_HISTORY_TTL));
+
+        assert_eq!(policy("POST", MATCH_DETAILS), CachePolicy::None);
+        assert_eq!(policy("GET", "https://glz-na-1.na.a.pvp.net/core-game/v1/matches/match"), CachePolicy::None);
+        assert_eq!(policy("GET", "https://glz-na-1.na.a.pvp.net/pregame/v1/matches/match"), CachePolicy::None);
+        assert_eq!(policy("GET", "https://127.0.0.1:1234/entitlements/v1/token"), CachePolicy::None);
+        assert_eq!(policy("GET", "not a url"), CachePolicy::None);
+    }
+
+    #[test]
+    fn responses_are_stored_and_listed() {
+        let (cache, dir) = cache("stored");
+        assert!(cache.get("GET", MATCH_DETAILS, CachePolicy::Immutable).is_none());
+
+        cache.put("GET", MATCH_DETAILS, CachePolicy::Immutable, &response("{}"));
+        cache.put("GET", MATCH_HISTORY, CachePolicy::Ttl(MATCH_HISTORY_TTL), &response("[1, 2]"));
+        cache.put("GET", "https://glz-na-1.na.a.pvp.net/core-game/v1/matches/match", CachePolicy::None, &response("{}"));
+
+        let cached = cache.get("GET", MATCH_DETAILS, CachePolicy::Immutable).unwrap();
+        assert_eq!(cached.status, 200);
+        assert_eq!(cached.header("Content-Type"), Some("application/json"));
+        assert_eq!(cached.body, b"{}");
+        // A route without a policy is never read from the cache.
+        assert!(cache.get("GET", MATCH_DETAILS, CachePolicy::None).is_none());
+
+        let mut entries = cache.entries();
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].url.as_str(), entries[0].expires_at, entries[0].size), (MATCH_DETAILS, None, 2));
+        assert_eq!(entries[1].url, MATCH_HISTORY);
+        assert_eq!(entries[1].expires_at, Some(entries[1].stored_at + MATCH_HISTORY_TTL.as_secs()));
+        assert_eq!(entries[1].size, 6);
+
+        assert_eq!(cache.clear(), 2);
+        assert!(cache.entries().is_empty());
+        assert!(cache.get("GET", MATCH_DETAILS, CachePolicy::Immutable).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn expired_entries_are_removed() {
+        let (cache, dir) = cache("expired");
+        cache.put("GET", MATCH_HISTORY, CachePolicy::Ttl(Duration::ZERO), &response("[]"));
+        assert_eq!(cache.entries().len(), 1);
+
+        assert!(cache.get("GET", MATCH_HISTORY, CachePolicy::Ttl(Duration::ZERO)).is_none());
+        assert!(cache.entries().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_is_cached_without_a_directory() {
+        let cache = ResponseCache::default();
+        cache.put("GET", MATCH_DETAILS, CachePolicy::Immutable, &response("{}"));
+        assert!(cache.get("GET", MATCH_DETAILS, CachePolicy::Immutable).is_none());
+        assert!(cache.entries().is_empty());
+        assert_eq!(cache.clear(), 0);
+    }
+}
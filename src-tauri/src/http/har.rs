@@ -0,0 +1,409 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::http::{HttpError, HttpRequest, RawResponse};
+
+/// Replaces secrets in a recording.
+const REDACTED: &str = "[REDACTED]";
+/// Request headers which are never written to a recording.
+const REDACTED_HEADERS: [&str; 2] = ["Authorization", "X-Riot-Entitlements-JWT"];
+/// Response headers which are never written to a recording.
+const REDACTED_RESPONSE_HEADERS: [&str; 1] = ["Set-Cookie"];
+/// JSON response fields holding tokens, e.g. from `entitlements/v1/token`.
+const REDACTED_FIELDS: [&str; 2] = ["accessToken", "token"];
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum RecorderError {
+    /// The app log directory could not be resolved.
+    NoLogDir,
+    /// The recording could not be written.
+    Io(String)
+}
+
+impl fmt::Display for RecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecorderError::NoLogDir => write!(f, "Unable to resolve the app log directory"),
+            RecorderError::Io(error) => write!(f, "Unable to write the recording: {}", error)
+        }
+    }
+}
+
+/// A recording in progress.
+/// It is written to disk when it is stopped.
+struct Recording {
+    path: PathBuf,
+    /// Redacted HAR entries.
+    entries: Vec<Value>,
+    /// Values seen in redacted headers and response fields.
+    /// These are also removed from URLs and bodies.
+    secrets: HashSet<String>
+}
+
+/// Records requests and responses to a HAR 1.2 file.
+#[derive(Default)]
+pub struct HarRecorder {
+    recording: Mutex<Option<Recording>>
+}
+
+impl HarRecorder {
+    /// Starts a new recording in the given directory.
+    /// Returns the path of the recording; if one is already
+    /// in progress, its path is returned instead.
+    pub fn start(&self, dir: Option<PathBuf>) -> Result<PathBuf, RecorderError> {
+        let mut recording = self.recording.lock().unwrap();
+        if let Some(recording) = recording.as_ref() {
+            return Ok(recording.path.clone());
+        }
+
+        let dir = match dir {
+            Some(dir) => dir,
+            None => return Err(RecorderError::NoLogDir)
+        };
+        if let Err(error) = fs::create_dir_all(&dir) {
+            return Err(RecorderError::Io(error.to_string()));
+        }
+
+        let path = dir.join(format!("requests-{}.har", Utc::now().format("%Y%m%d-%H%M%S")));
+        let started = Recording { path: path.clone(), entries: Vec::new(), secrets: HashSet::new() };
+        if let Err(error) = write(&started) {
+            return Err(RecorderError::Io(error.to_string()));
+        }
+
+        *recording = Some(started);
+        Ok(path)
+    }
+
+    /// Stops the current recording and writes it to disk.
+    /// Returns its path, if one was in progress.
+    pub fn stop(&self) -> Result<Option<PathBuf>, RecorderError> {
+        let recording = match self.recording.lock().unwrap().take() {
+            Some(recording) => recording,
+            None => return Ok(None)
+        };
+
+        match write(&recording) {
+            Ok(_) => Ok(Some(recording.path)),
+            Err(error) => Err(RecorderError::Io(error.to_string()))
+        }
+    }
+
+    /// Adds a request and its outcome to the current recording, if any.
+    /// `secrets` are redacted wherever they appear.
+    pub fn record(
+        &self,
+        request: &HttpRequest,
+        result: &Result<RawResponse, HttpError>,
+        started: DateTime<Utc>,
+        elapsed: Duration,
+        secrets: &[String]
+    ) {
+        let mut recording = self.recording.lock().unwrap();
+        let recording = match recording.as_mut() {
+            Some(recording) => recording,
+            None => return
+        };
+
+        recording.secrets.extend(secrets.iter().cloned());
+        for (name, value) in request.headers.iter().flatten() {
+            if is_redacted(name) {
+                recording.secrets.insert(value.clone());
+                if let Some((_, token)) = value.split_once(' ') {
+                    recording.secrets.insert(token.to_string());
+                }
+            }
+        }
+
+        let mut entry = entry(request, result, started, elapsed, &mut recording.secrets);
+        redact_secrets(&mut entry, &recording.secrets);
+        recording.entries.push(entry);
+    }
+}
+
+/// Whether a request header is removed from recordings.
+fn is_redacted(name: &str) -> bool {
+    REDACTED_HEADERS.iter().any(|redacted| redacted.eq_ignore_ascii_case(name))
+}
+
+/// Whether a response header is removed from recordings.
+fn is_redacted_response(name: &str) -> bool {
+    REDACTED_RESPONSE_HEADERS.iter().any(|redacted| redacted.eq_ignore_ascii_case(name))
+}
+
+/// Replaces every token field in a JSON value, at any depth.
+/// The replaced values are added to `secrets`.
+/// Returns false if there were no token fields.
+fn redact_fields(value: &mut Value, secrets: &mut HashSet<String>) -> bool {
+    let mut redacted = false;
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if !REDACTED_FIELDS.contains(&name.as_str()) {
+                    redacted |= redact_fields(field, secrets);
+                    continue;
+                }
+
+                if let Some(secret) = field.as_str() {
+                    secrets.insert(secret.to_string());
+                }
+                *field = json!(REDACTED);
+                redacted = true;
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redacted |= redact_fields(value, secrets);
+            }
+        }
+        _ => {}
+    }
+
+    redacted
+}
+
+/// Replaces every secret in the strings of a JSON value, at any depth.
+fn redact_secrets(value: &mut Value, secrets: &HashSet<String>) {
+    match value {
+        Value::String(text) => {
+            for secret in secrets {
+                if !secret.is_empty() && text.contains(secret.as_str()) {
+                    *text = text.replace(secret.as_str(), REDACTED);
+                }
+            }
+        }
+        Value::Object(fields) => fields.values_mut().for_each(|field| redact_secrets(field, secrets)),
+        Value::Array(values) => values.iter_mut().for_each(|value| redact_secrets(value, secrets)),
+        _ => {}
+    }
+}
+
+/// Writes the whole recording to disk.
+fn write(recording: &Recording) -> std::io::Result<()> {
+    let har = json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION")
+            },
+            "entries": recording.entries
+        }
+    });
+
+    fs::write(&recording.path, serde_json::to_string_pretty(&har).unwrap())
+}
+
+/// Creates a HAR entry for a request and its outcome.
+fn entry(
+    request: &HttpRequest,
+    result: &Result<RawResponse, HttpError>,
+    started: DateTime<Utc>,
+    elapsed: Duration,
+    secrets: &mut HashSet<String>
+) -> Value {
+    let time = elapsed.as_secs_f64() * 1000.0;
+    let mut entry = json!({
+        "startedDateTime": started.to_rfc3339(),
+        "time": time,
+        "request": request_entry(request),
+        "response": response_entry(result, secrets),
+        "cache": {},
+        "timings": { "send": 0, "wait": time, "receive": 0 }
+    });
+
+    if let Err(error) = result {
+        entry["_error"] = json!(error.to_string());
+    }
+
+    entry
+}
+
+fn request_entry(request: &HttpRequest) -> Value {
+    let headers: Vec<Value> = request.headers.iter().flatten()
+        .map(|(name, value)| json!({
+            "name": name,
+            "value": if is_redacted(name) { REDACTED } else { value.as_str() }
+        }))
+        .collect();
+
+    let query: Vec<Value> = match Url::parse(&request.url) {
+        Ok(url) => url.query_pairs()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect(),
+        Err(_) => Vec::new()
+    };
+
+    let body = request.body.clone()
+        .and_then(|body| body.decode(request.body_encoding.unwrap_or_default()).ok());
+
+    let mut entry = json!({
+        "method": request.method.to_uppercase(),
+        "url": request.url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": headers,
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": body.as_ref().map_or(0, |body| body.len())
+    });
+
+    if let Some(body) = body {
+        let mime_type = request.headers.iter().flatten()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+            .map_or("", |(_, value)| value.as_str());
+        entry["postData"] = json!({
+            "mimeType": mime_type,
+            "text": String::from_utf8_lossy(&body)
+        });
+    }
+
+    entry
+}
+
+/// Token fields in JSON bodies are replaced, and their values added to `secrets`.
+fn response_entry(result: &Result<RawResponse, HttpError>, secrets: &mut HashSet<String>) -> Value {
+    let response = match result {
+        Ok(response) => response,
+        Err(_) => return json!({
+            "status": 0,
+            "statusText": "",
+            "httpVersion": "",
+            "cookies": [],
+            "headers": [],
+            "content": { "size": 0, "mimeType": "" },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1
+        })
+    };
+
+    let headers: Vec<Value> = response.headers.iter()
+        .map(|header| {
+            let value = match &header.value {
+                _ if is_redacted_response(&header.name) => REDACTED.to_string(),
+                Some(value) => value.clone(),
+                None => String::from_utf8_lossy(header.raw.as_deref().unwrap_or_default()).to_string()
+            };
+            json!({ "name": header.name, "value": value })
+        })
+        .collect();
+
+    let mut content = json!({
+        "size": response.body.len(),
+        "mimeType": response.header("Content-Type").unwrap_or("")
+    });
+    // Token fields are redacted whether or not the tokens were used in a request.
+    let mut body = serde_json::from_slice::<Value>(&response.body).ok();
+    let redacted = body.as_mut().map_or(false, |body| redact_fields(body, secrets));
+    match std::str::from_utf8(&response.body) {
+        _ if redacted => content["text"] = json!(body.unwrap_or_default().to_string()),
+        Ok(text) => content["text"] = json!(text),
+        Err(_) => {
+            content["text"] = json!(BASE64_STANDARD.encode(&response.body));
+            content["encoding"] = json!("base64");
+        }
+    }
+
+    json!({
+        "status": response.status,
+        "statusText": reqwest::StatusCode::from_u16(response.status).ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or(""),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": headers,
+        "content": content,
+        "redirectURL": response.header("Location").unwrap_or(""),
+        "headersSize": -1,
+        "bodySize": response.body.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpHeader;
+
+    #[test]
+    fn secrets_are_kept_once_and_written_on_stop() {
+        let dir = std::env::temp_dir().join(format!("guilded-stats-har-once-{}", std::process::id()));
+        let recorder = HarRecorder::default();
+        let path = recorder.start(Some(dir.clone())).unwrap();
+
+        let request = HttpRequest::new("GET", "https://pd.na.a.pvp.net/?puuid=password")
+            .header("Authorization", "Bearer access")
+            .header("X-Riot-Entitlements-JWT", "entitlement");
+        for _ in 0..100 {
+            recorder.record(&request, &Err(HttpError::Timeout("timed out".to_string())),
+                Utc::now(), Duration::ZERO, &["password".to_string()]);
+        }
+        {
+            let recording = recorder.recording.lock().unwrap();
+            let recording = recording.as_ref().unwrap();
+            assert_eq!(recording.entries.len(), 100);
+            assert_eq!(recording.secrets.len(), 4);
+        }
+
+        // Entries are only written once the recording stops.
+        let har: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(har["log"]["entries"], json!([]));
+
+        assert_eq!(recorder.stop().unwrap(), Some(path.clone()));
+        assert_eq!(recorder.stop().unwrap(), None);
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let har: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(har["log"]["entries"].as_array().unwrap().len(), 100);
+        assert_eq!(har["log"]["entries"][0]["request"]["url"], format!("https://pd.na.a.pvp.net/?puuid={}", REDACTED));
+        for secret in ["access", "entitlement", "password"] {
+            assert!(!text.contains(secret), "{} was recorded", secret);
+        }
+    }
+
+    #[test]
+    fn response_tokens_and_cookies_are_redacted() {
+        let dir = std::env::temp_dir().join(format!("guilded-stats-har-{}", std::process::id()));
+        let recorder = HarRecorder::default();
+        let path = recorder.start(Some(dir.clone())).unwrap();
+
+        // The tokens are only ever seen in this response, never in a request header.
+        let response = RawResponse {
+            status: 200,
+            headers: vec![HttpHeader {
+                name: "set-cookie".to_string(),
+                value: Some("ssid=cookie-secret; Path=/".to_string()),
+                raw: None
+            }],
+            body: br#"{"accessToken":"access-secret","entitlements":[],"token":"entitlement-secret"}"#.to_vec()
+        };
+        let request = HttpRequest::new("GET", "https://127.0.0.1:1234/entitlements/v1/token");
+        recorder.record(&request, &Ok(response), Utc::now(), Duration::ZERO, &[]);
+
+        // Later uses of the tokens are redacted too.
+        let request = HttpRequest::new("GET", "https://127.0.0.1:1234/?token=access-secret");
+        recorder.record(&request, &Err(HttpError::Timeout("timed out".to_string())), Utc::now(), Duration::ZERO, &[]);
+        assert_eq!(recorder.stop().unwrap(), Some(path.clone()));
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        for secret in ["access-secret", "entitlement-secret", "cookie-secret"] {
+            assert!(!text.contains(secret), "{} was recorded", secret);
+        }
+
+        let har: Value = serde_json::from_str(&text).unwrap();
+        let response = &har["log"]["entries"][0]["response"];
+        assert_eq!(response["headers"][0]["value"], REDACTED);
+        let body: Value = serde_json::from_str(response["content"]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(body, json!({ "accessToken": REDACTED, "entitlements": [], "token": REDACTED }));
+    }
+}
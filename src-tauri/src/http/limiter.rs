@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+/// Requests which may be sent to a host at once.
+const BURST: f64 = 10.0;
+/// Requests allowed per second per host once the burst is used up.
+const REFILL_PER_SECOND: f64 = 1.0;
+/// How long to back off after a 429 without a usable `Retry-After`.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+/// The longest a host is backed off for after a 429.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// The number of requests waiting for a host.
+/// Emitted to the webview as `http-queue`.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueueDepth {
+    pub host: String,
+    pub depth: usize
+}
+
+/// A token bucket for a single host.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Set after a 429; no requests are sent before this time.
+    blocked_until: Option<Instant>,
+    /// Requests waiting for a token.
+    queued: usize
+}
+
+impl Bucket {
+    fn new(now: Instant) -> Self {
+        Self {
+            tokens: BURST,
+            updated: now,
+            blocked_until: None,
+            queued: 0
+        }
+    }
+
+    /// Takes a token, or returns how long to wait for one.
+    fn take(&mut self, now: Instant) -> Option<Duration> {
+        if let Some(until) = self.blocked_until {
+            if now < until {
+                return Some(until - now);
+            }
+            self.blocked_until = None;
+        }
+
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * REFILL_PER_SECOND).min(BURST);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / REFILL_PER_SECOND))
+        }
+    }
+
+    /// Blocks the bucket for the given duration, up to `MAX_RETRY_AFTER`.
+    /// Tokens only start to refill once the block ends.
+    fn block(&mut self, now: Instant, duration: Duration) {
+        let until = now.checked_add(duration.min(MAX_RETRY_AFTER)).unwrap_or(now);
+        self.tokens = 0.0;
+        self.updated = until;
+        self.blocked_until = Some(until);
+    }
+}
+
+/// A per-host token bucket rate limiter.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>
+}
+
+impl RateLimiter {
+    /// Takes a token for the host, or returns how long to wait for one.
+    pub fn try_acquire(&self, host: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.entry(host.to_string())
+            .or_insert_with(|| Bucket::new(now))
+            .take(now)
+    }
+
+    /// Blocks all requests to the host for the given duration.
+    pub fn block(&self, host: &str, duration: Duration) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.entry(host.to_string())
+            .or_insert_with(|| Bucket::new(now))
+            .block(now, duration);
+    }
+
+    /// Marks a request to the host as queued.
+    /// Returns the new queue depth.
+    pub fn enqueue(&self, host: &str) -> usize {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(host.to_string()).or_insert_with(|| Bucket::new(now));
+        bucket.queued += 1;
+        bucket.queued
+    }
+
+    /// Marks a queued request to the host as no longer waiting.
+    /// Returns the new queue depth.
+    pub fn dequeue(&self, host: &str) -> usize {
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(host) {
+            Some(bucket) => {
+                bucket.queued = bucket.queued.saturating_sub(1);
+                bucket.queued
+            }
+            None => 0
+        }
+    }
+
+    /// Returns the queue depth of every known host.
+    pub fn depths(&self) -> Vec<QueueDepth> {
+        self.buckets.lock().unwrap().iter()
+            .map(|(host, bucket)| QueueDepth { host: host.clone(), depth: bucket.queued })
+            .collect()
+    }
+}
+
+/// Reads the delay from a `Retry-After` header given in seconds, up to `MAX_RETRY_AFTER`.
+/// HTTP dates are not supported and use the default delay.
+pub fn parse_retry_after(value: Option<&str>) -> Duration {
+    value
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|seconds| Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+        .unwrap_or(DEFAULT_RETRY_AFTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_allows_a_burst_then_refills() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(now);
+        for _ in 0..10 {
+            assert_eq!(bucket.take(now), None);
+        }
+        assert_eq!(bucket.take(now), Some(Duration::from_secs(1)));
+        assert_eq!(bucket.take(now + Duration::from_millis(500)), Some(Duration::from_millis(500)));
+        assert_eq!(bucket.take(now + Duration::from_secs(1)), None);
+
+        // The bucket never holds more than the burst.
+        let later = now + Duration::from_secs(100);
+        for _ in 0..10 {
+            assert_eq!(bucket.take(later), None);
+        }
+        assert_eq!(bucket.take(later), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn block_waits_then_refills_from_empty() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(now);
+        bucket.block(now, Duration::from_secs(5));
+
+        assert_eq!(bucket.take(now), Some(Duration::from_secs(5)));
+        assert_eq!(bucket.take(now + Duration::from_secs(1)), Some(Duration::from_secs(4)));
+        assert_eq!(bucket.take(now + Duration::from_secs(5)), Some(Duration::from_secs(1)));
+        assert_eq!(bucket.take(now + Duration::from_secs(6)), None);
+    }
+
+    #[test]
+    fn block_is_capped() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(now);
+        bucket.block(now, Duration::MAX);
+
+        assert_eq!(bucket.take(now), Some(MAX_RETRY_AFTER));
+        assert_eq!(bucket.take(now + MAX_RETRY_AFTER), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn block_only_affects_one_host() {
+        let limiter = RateLimiter::default();
+        limiter.block("blocked.a.pvp.net", Duration::from_secs(30));
+
+        let wait = limiter.try_acquire("blocked.a.pvp.net").unwrap();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30), "{:?}", wait);
+        assert_eq!(limiter.try_acquire("other.a.pvp.net"), None);
+    }
+
+    #[test]
+    fn parse_retry_after_reads_seconds() {
+        assert_eq!(parse_retry_after(Some("12")), Duration::from_secs(12));
+        assert_eq!(parse_retry_after(Some(" 3 ")), Duration::from_secs(3));
+        assert_eq!(parse_retry_after(Some("0")), Duration::ZERO);
+        assert_eq!(parse_retry_after(Some("-1")), DEFAULT_RETRY_AFTER);
+        assert_eq!(parse_retry_after(Some("Wed, 21 Oct 2015 07:28:00 GMT")), DEFAULT_RETRY_AFTER);
+        assert_eq!(parse_retry_after(None), DEFAULT_RETRY_AFTER);
+
+        assert_eq!(parse_retry_after(Some("86400")), MAX_RETRY_AFTER);
+        assert_eq!(parse_retry_after(Some("18446744073709551615")), MAX_RETRY_AFTER);
+        assert_eq!(parse_retry_after(Some("18446744073709551616")), DEFAULT_RETRY_AFTER);
+    }
+}
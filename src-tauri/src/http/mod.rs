@@ -0,0 +1,1079 @@
+mod cache;
+mod har;
+mod limiter;
+mod policy;
+mod proxy;
+mod replay;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use base64::prelude::*;
+use chrono::Utc;
+use futures_util::{stream, StreamExt};
+use futures_util::future::{AbortHandle, Abortable, Aborted};
+use log::{info, warn};
+use reqwest::{Client, Method, Url};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::redirect::{Action, Attempt, Policy};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+use crate::lockfile::LockfileState;
+use self::cache::{CacheEntryInfo, ResponseCache};
+use self::har::{HarRecorder, RecorderError};
+use self::limiter::{QueueDepth, RateLimiter};
+use self::replay::Replay;
+
+pub use self::proxy::ProxyConfig;
+pub use self::replay::ReplayConfig;
+
+/// How many times a request is queued again after a 429 before giving up.
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+/// How many requests in a `fetch_many` batch are sent at once by default.
+const DEFAULT_CONCURRENCY: usize = 4;
+/// The connect timeouts clients are built with, in milliseconds.
+/// Each needs its own client, so requested timeouts are rounded up to one of these.
+const CONNECT_TIMEOUTS_MS: [u64; 7] = [250, 500, 1_000, 2_000, 5_000, 10_000, 30_000];
+
+#[derive(Clone, Deserialize)]
+pub struct HttpRequest {
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    body: Option<HttpBody>,
+    method: String,
+    /// How a string `body` is encoded. Defaults to UTF-8.
+    body_encoding: Option<BodyEncoding>,
+    /// How the response body should be encoded. Defaults to UTF-8.
+    response_encoding: Option<BodyEncoding>,
+    /// Set to `json` to parse the response body before returning it.
+    response_type: Option<ResponseType>,
+    /// Time allowed for the whole request, in milliseconds.
+    timeout_ms: Option<u64>,
+    /// Time allowed to establish the connection, in milliseconds.
+    /// Rounded up to 0.25, 0.5, 1, 2, 5, 10 or 30 seconds; longer times are not limited.
+    connect_timeout_ms: Option<u64>,
+    /// Identifies the request for `cancel_fetch`.
+    request_id: Option<String>
+}
+
+#[derive(Clone, Serialize)]
+pub struct HttpResponse {
+    success: bool,
+    pub(crate) status: u16,
+    pub(crate) body: HttpBody,
+    /// How `body` is encoded.
+    pub(crate) encoding: BodyEncoding,
+    /// Every header value, in the order they were received.
+    pub(crate) headers: Vec<HttpHeader>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HttpHeader {
+    name: String,
+    /// The value, if it is valid UTF-8.
+    value: Option<String>,
+    /// The raw value, if it is not valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw: Option<Vec<u8>>
+}
+
+impl HttpHeader {
+    fn new(name: &HeaderName, value: &HeaderValue) -> Self {
+        match std::str::from_utf8(value.as_bytes()) {
+            Ok(text) => Self {
+                name: name.as_str().to_string(),
+                value: Some(text.to_string()),
+                raw: None
+            },
+            Err(_) => Self {
+                name: name.as_str().to_string(),
+                value: None,
+                raw: Some(value.as_bytes().to_vec())
+            }
+        }
+    }
+}
+
+/// A request or response body.
+/// Strings are UTF-8 text or base64; byte arrays are raw bytes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum HttpBody {
+    Text(String),
+    Bytes(Vec<u8>),
+    /// A parsed JSON document. Sent as its serialized text.
+    Json(Value)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseType {
+    /// The body is returned as-is, using `response_encoding`.
+    Text,
+    /// The body is parsed as JSON. `response_encoding` is ignored.
+    Json
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyEncoding {
+    Utf8,
+    Base64,
+    /// Sent over IPC as an array of bytes.
+    Bytes
+}
+
+impl Default for BodyEncoding {
+    fn default() -> Self {
+        BodyEncoding::Utf8
+    }
+}
+
+impl HttpBody {
+    /// Converts raw bytes into a body with the given encoding.
+    fn encode(bytes: Vec<u8>, encoding: BodyEncoding) -> Result<Self, HttpError> {
+        match encoding {
+            BodyEncoding::Utf8 => match String::from_utf8(bytes) {
+                Ok(text) => Ok(HttpBody::Text(text)),
+                Err(error) => Err(HttpError::Decode(format!(
+                    "body is not valid UTF-8 ({}); use the base64 or bytes encoding", error)))
+            },
+            BodyEncoding::Base64 => Ok(HttpBody::Text(BASE64_STANDARD.encode(bytes))),
+            BodyEncoding::Bytes => Ok(HttpBody::Bytes(bytes))
+        }
+    }
+
+    /// Parses raw bytes as a JSON body.
+    /// An empty body is treated as `null`.
+    fn parse_json(bytes: &[u8]) -> Result<Self, HttpError> {
+        if bytes.iter().all(|byte| byte.is_ascii_whitespace()) {
+            return Ok(HttpBody::Json(Value::Null));
+        }
+
+        match serde_json::from_slice(bytes) {
+            Ok(value) => Ok(HttpBody::Json(value)),
+            Err(error) => Err(HttpError::Json {
+                message: error.to_string(),
+                line: error.line(),
+                column: error.column()
+            })
+        }
+    }
+
+    /// Returns the raw bytes of a body with the given encoding.
+    fn decode(self, encoding: BodyEncoding) -> Result<Vec<u8>, HttpError> {
+        match self {
+            HttpBody::Bytes(bytes) => Ok(bytes),
+            HttpBody::Json(value) => Ok(value.to_string().into_bytes()),
+            HttpBody::Text(text) if encoding == BodyEncoding::Base64 => {
+                match BASE64_STANDARD.decode(text) {
+                    Ok(bytes) => Ok(bytes),
+                    Err(error) => Err(HttpError::InvalidBody(error.to_string()))
+                }
+            }
+            HttpBody::Text(text) => Ok(text.into_bytes())
+        }
+    }
+}
+
+/// The outcome of one request in a `fetch_many` batch.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchResult {
+    Ok(HttpResponse),
+    Err(HttpError)
+}
+
+/// Emitted as `http-progress` when a request in a batch completes.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchProgress {
+    pub batch_id: Option<String>,
+    /// The index of the completed request in the batch.
+    pub index: usize,
+    pub success: bool,
+    /// The number of requests completed so far.
+    pub completed: usize,
+    pub total: usize
+}
+
+/// A response as received, before the body is encoded for the webview.
+#[derive(Clone, Debug)]
+pub(crate) struct RawResponse {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<HttpHeader>,
+    pub(crate) body: Vec<u8>
+}
+
+impl RawResponse {
+    /// Returns the first UTF-8 value of a header.
+    /// Header names are case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name) && header.value.is_some())
+            .and_then(|header| header.value.as_deref())
+    }
+
+    /// Encodes the body as the request asked for.
+    fn into_response(self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+        if request.response_type == Some(ResponseType::Json) {
+            return Ok(HttpResponse {
+                success: true,
+                status: self.status,
+                headers: self.headers,
+                body: HttpBody::parse_json(&self.body)?,
+                encoding: BodyEncoding::Utf8
+            });
+        }
+
+        let encoding = request.response_encoding.unwrap_or_default();
+        Ok(HttpResponse {
+            success: true,
+            status: self.status,
+            headers: self.headers,
+            body: HttpBody::encode(self.body, encoding)?,
+            encoding
+        })
+    }
+}
+
+impl HttpResponse {
+    /// Returns the raw response body.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.body.clone().decode(self.encoding).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum HttpError {
+    /// The HTTP client could not be created.
+    Client(String),
+    /// The request URL could not be parsed.
+    InvalidUrl(String),
+    /// The request method is not a valid HTTP method.
+    InvalidMethod(String),
+    /// A request header name or value is invalid.
+    InvalidHeader(String),
+    /// The request body does not match its encoding.
+    InvalidBody(String),
+    /// DNS resolution or the TCP connection failed.
+    Connect(String),
+    /// The TLS handshake failed.
+    Tls(String),
+    /// The request timed out.
+    Timeout(String),
+    /// The redirect limit was reached or a redirect loop was detected.
+    Redirect(String),
+    /// The response body could not be read or decoded.
+    Decode(String),
+    /// The response body is not valid JSON.
+    Json { message: String, line: usize, column: usize },
+    /// The request was cancelled with `cancel_fetch`.
+    Cancelled(String),
+    /// Another request with the same `request_id` is still in flight.
+    DuplicateRequest(String),
+    /// The URL is not on the outbound allowlist.
+    Forbidden(String),
+    /// Any other failure while sending the request.
+    Request(String)
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Client(error) => write!(f, "Failed to create HTTP client: {}", error),
+            HttpError::InvalidUrl(error) => write!(f, "Invalid URL: {}", error),
+            HttpError::InvalidMethod(method) => write!(f, "Invalid HTTP method: {}", method),
+            HttpError::InvalidHeader(error) => write!(f, "Invalid HTTP header: {}", error),
+            HttpError::InvalidBody(error) => write!(f, "Invalid request body: {}", error),
+            HttpError::Connect(error) => write!(f, "Failed to connect: {}", error),
+            HttpError::Tls(error) => write!(f, "TLS handshake failed: {}", error),
+            HttpError::Timeout(error) => write!(f, "Request timed out: {}", error),
+            HttpError::Redirect(error) => write!(f, "Too many redirects: {}", error),
+            HttpError::Decode(error) => write!(f, "Failed to decode response body: {}", error),
+            HttpError::Json { message, .. } => write!(f, "Failed to parse response JSON: {}", message),
+            HttpError::Cancelled(request_id) => write!(f, "Request {} was cancelled.", request_id),
+            HttpError::DuplicateRequest(request_id) => write!(f, "Request {} is already in flight.", request_id),
+            HttpError::Forbidden(error) => write!(f, "URL not allowed: {}", error),
+            HttpError::Request(error) => write!(f, "Failed to send HTTP request: {}", error)
+        }
+    }
+}
+
+/// Shared HTTP clients, kept in Tauri managed state.
+/// Reusing them keeps connections and TLS sessions alive between requests.
+pub struct HttpState {
+    /// Clients by policy, created on first use.
+    clients: Mutex<HashMap<ClientPolicy, Client>>,
+    /// The port of the local Riot Client API, from the lockfile.
+    local_port: Mutex<Option<u16>>,
+    /// Requests which can be cancelled with `cancel_fetch`, by request ID.
+    in_flight: Mutex<HashMap<String, AbortHandle>>,
+    /// Limits requests to remote hosts.
+    limiter: RateLimiter,
+    /// Stores responses of cacheable routes on disk.
+    cache: ResponseCache,
+    /// Records requests to a HAR file while enabled.
+    recorder: HarRecorder,
+    /// Answers requests from fixtures instead of the network, if enabled.
+    replay: Option<Replay>,
+    /// Hosts serving game assets which may be requested, from `plugins.http.assetHosts`.
+    /// Subdomain wildcards (`*.example.com`) are allowed.
+    asset_hosts: Mutex<Vec<String>>,
+    /// The proxy for outbound connections, if any.
+    proxy: Mutex<Option<ProxyConfig>>,
+    /// Used to emit events to the webview once the app is running.
+    app: Mutex<Option<AppHandle>>
+}
+
+/// The settings a client is built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ClientPolicy {
+    /// Accept invalid certificates. Only used for the local Riot Client.
+    local: bool,
+    /// One of `CONNECT_TIMEOUTS_MS`, if set.
+    connect_timeout_ms: Option<u64>
+}
+
+impl ClientPolicy {
+    fn build(&self, proxy: Option<&ProxyConfig>) -> Result<Client, HttpError> {
+        let mut builder = Client::builder()
+            .redirect(Policy::custom(redirect))
+            .tcp_keepalive(Duration::from_secs(60))
+            .danger_accept_invalid_certs(self.local);
+        if let Some(timeout) = self.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy.reqwest_proxy()?);
+        }
+
+        match builder.build() {
+            Ok(client) => Ok(client),
+            Err(error) => Err(HttpError::Client(describe(&error)))
+        }
+    }
+}
+
+/// Follows up to 15 redirects on the same host.
+/// Redirects to another host are returned to the caller, so they
+/// cannot bypass the outbound allowlist.
+fn redirect(attempt: Attempt) -> Action {
+    if attempt.previous().len() > 15 {
+        return attempt.error("too many redirects");
+    }
+
+    let same_host = attempt.previous().first()
+        .map_or(false, |first| first.host_str() == attempt.url().host_str()
+            && first.port_or_known_default() == attempt.url().port_or_known_default());
+    if same_host {
+        attempt.follow()
+    } else {
+        attempt.stop()
+    }
+}
+
+impl HttpState {
+    pub fn new(replay: Option<ReplayConfig>) -> Result<Self, HttpError> {
+        // Build the default clients up front to surface errors early.
+        let mut clients = HashMap::new();
+        for local in [true, false] {
+            let policy = ClientPolicy { local, connect_timeout_ms: None };
+            clients.insert(policy, policy.build(None)?);
+        }
+
+        Ok(Self {
+            clients: Mutex::new(clients),
+            local_port: Mutex::new(None),
+            in_flight: Mutex::new(HashMap::new()),
+            limiter: RateLimiter::default(),
+            cache: ResponseCache::default(),
+            recorder: HarRecorder::default(),
+            replay: replay.map(Replay::new),
+            asset_hosts: Mutex::new(Vec::new()),
+            proxy: Mutex::new(None),
+            app: Mutex::new(None)
+        })
+    }
+
+    /// Attaches the app handle, so events can be emitted to the webview.
+    pub fn attach(&self, app: AppHandle) {
+        self.cache.set_directory(app.path_resolver().app_cache_dir().map(|dir| dir.join("http")));
+        let asset_hosts = app.config().plugins.0.get("http")
+            .and_then(|config| config.get("assetHosts"))
+            .and_then(|hosts| serde_json::from_value::<Vec<String>>(hosts.clone()).ok())
+            .unwrap_or_default();
+        *self.asset_hosts.lock().unwrap() = asset_hosts;
+
+        // Fixtures and the proxy are loaded once logging is available.
+        if let Some(replay) = &self.replay {
+            replay.load();
+        }
+        if let Some(proxy) = ProxyConfig::from_env() {
+            info!("Using the proxy at {}.", proxy.url);
+            if let Err(error) = self.set_proxy(Some(proxy)) {
+                warn!("Unable to use the proxy: {}", error);
+            }
+        }
+        *self.app.lock().unwrap() = Some(app);
+    }
+
+    /// Returns values which must not be written to recordings.
+    fn secrets(&self) -> Vec<String> {
+        let app = self.app.lock().unwrap();
+        let lockfile = app.as_ref().and_then(|app| app.state::<LockfileState>().0.lock().unwrap().clone());
+        lockfile.map(|info| vec![info.password]).unwrap_or_default()
+    }
+
+    /// Emits an event to the webview, if the app is running.
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(app) = self.app.lock().unwrap().as_ref() {
+            if let Err(error) = app.emit_all(event, payload) {
+                warn!("Unable to emit '{}': {}", event, error);
+            }
+        }
+    }
+
+    /// Sets the port of the local Riot Client API.
+    /// Only `127.0.0.1` on this port may use an invalid certificate.
+    pub fn set_local_port(&self, port: Option<u16>) {
+        *self.local_port.lock().unwrap() = port;
+    }
+
+    /// Returns the proxy for outbound connections, if any.
+    pub fn proxy(&self) -> Option<ProxyConfig> {
+        self.proxy.lock().unwrap().clone()
+    }
+
+    /// Sets the proxy for outbound connections.
+    /// Existing clients are dropped, so new requests use the proxy.
+    pub fn set_proxy(&self, proxy: Option<ProxyConfig>) -> Result<(), HttpError> {
+        if let Some(proxy) = &proxy {
+            proxy.validate()?;
+        }
+
+        let mut clients = self.clients.lock().unwrap();
+        *self.proxy.lock().unwrap() = proxy;
+        clients.clear();
+
+        Ok(())
+    }
+
+    /// Returns the client to use for the given URL.
+    fn client(&self, url: &Url, connect_timeout_ms: Option<u64>) -> Result<Client, HttpError> {
+        let local_port = *self.local_port.lock().unwrap();
+        let local = local_port.is_some()
+            && url.host_str() == Some("127.0.0.1")
+            && url.port() == local_port;
+        let policy = ClientPolicy { local, connect_timeout_ms: round_connect_timeout(connect_timeout_ms) };
+
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&policy) {
+            return Ok(client.clone());
+        }
+
+        let client = policy.build(self.proxy.lock().unwrap().as_ref())?;
+        clients.insert(policy, client.clone());
+
+        Ok(client)
+    }
+
+    /// Checks a URL against the outbound allowlist.
+    fn check(&self, url: &str) -> Result<(), HttpError> {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(error) => return Err(HttpError::InvalidUrl(error.to_string()))
+        };
+
+        let local_port = *self.local_port.lock().unwrap();
+        policy::check(&url, local_port, &self.asset_hosts.lock().unwrap())
+    }
+
+    /// Aborts an in-flight request.
+    /// Returns false if no request has the given ID.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        // Abort while holding the lock, so `send` sees the handle was removed here.
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.remove(request_id) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false
+        }
+    }
+}
+
+/// Rounds a connect timeout up to the nearest one in `CONNECT_TIMEOUTS_MS`,
+/// so at most a few clients are ever created.
+/// Timeouts longer than all of them are dropped.
+fn round_connect_timeout(timeout_ms: Option<u64>) -> Option<u64> {
+    let timeout_ms = timeout_ms?;
+    CONNECT_TIMEOUTS_MS.iter().copied().find(|rounded| *rounded >= timeout_ms)
+}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(error: reqwest::Error) -> Self {
+        let message = describe(&error);
+
+        if error.is_timeout() {
+            HttpError::Timeout(message)
+        } else if error.is_redirect() {
+            HttpError::Redirect(message)
+        } else if is_tls_error(&error) {
+            HttpError::Tls(message)
+        } else if error.is_connect() {
+            HttpError::Connect(message)
+        } else if error.is_decode() || error.is_body() {
+            HttpError::Decode(message)
+        } else if error.is_builder() {
+            HttpError::Client(message)
+        } else {
+            HttpError::Request(message)
+        }
+    }
+}
+
+/// Joins an error and all of its sources into one message.
+fn describe(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+
+    message
+}
+
+/// Checks if an error was caused by the TLS handshake.
+fn is_tls_error(error: &dyn Error) -> bool {
+    let mut source = error.source();
+    while let Some(error) = source {
+        if error.downcast_ref::<native_tls::Error>().is_some() {
+            return true;
+        }
+        source = error.source();
+    }
+
+    false
+}
+
+impl HttpRequest {
+    /// Creates a request without headers or a body.
+    pub fn new(method: &str, url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: None,
+            body: None,
+            method: method.to_string(),
+            body_encoding: None,
+            response_encoding: None,
+            response_type: None,
+            timeout_ms: None,
+            connect_timeout_ms: None,
+            request_id: None
+        }
+    }
+
+    /// Adds a header to the request.
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers
+            .get_or_insert_with(HashMap::new)
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+#[tauri::command]
+pub async fn fetch(http: State<'_, HttpState>, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+    send(&http, request).await
+}
+
+/// Performs several HTTP requests, at most `concurrency` at a time.
+/// Results are returned in the order of the requests;
+/// `http-progress` is emitted as each request completes.
+#[tauri::command]
+pub async fn fetch_many(
+    http: State<'_, HttpState>,
+    requests: Vec<HttpRequest>,
+    concurrency: Option<usize>,
+    batch_id: Option<String>
+) -> Result<Vec<FetchResult>, HttpError> {
+    let total = requests.len();
+    let completed = AtomicUsize::new(0);
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+
+    let http = &*http;
+    let (completed, batch_id) = (&completed, &batch_id);
+    let results = stream::iter(requests.into_iter().enumerate())
+        .map(|(index, request)| async move {
+            let result = send(http, request).await;
+            http.emit("http-progress", FetchProgress {
+                batch_id: batch_id.clone(),
+                index,
+                success: result.is_ok(),
+                completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                total
+            });
+
+            match result {
+                Ok(response) => FetchResult::Ok(response),
+                Err(error) => FetchResult::Err(error)
+            }
+        })
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    Ok(results)
+}
+
+/// Cancels the in-flight request with the given ID.
+/// Returns false if the request already completed.
+#[tauri::command]
+pub fn cancel_fetch(http: State<'_, HttpState>, request_id: String) -> bool {
+    http.cancel(&request_id)
+}
+
+/// Returns the queue depth of every rate-limited host.
+#[tauri::command]
+pub fn http_queue(http: State<'_, HttpState>) -> Vec<QueueDepth> {
+    http.limiter.depths()
+}
+
+/// Lists every entry in the response cache.
+#[tauri::command]
+pub fn http_cache(http: State<'_, HttpState>) -> Vec<CacheEntryInfo> {
+    http.cache.entries()
+}
+
+/// Removes every entry from the response cache.
+/// Returns the number of entries removed.
+#[tauri::command]
+pub fn http_cache_clear(http: State<'_, HttpState>) -> usize {
+    http.cache.clear()
+}
+
+/// Starts recording requests to a HAR file in the app log directory.
+/// Returns the path of the recording.
+#[tauri::command]
+pub fn har_start(app: AppHandle, http: State<'_, HttpState>) -> Result<PathBuf, RecorderError> {
+    let path = http.recorder.start(app.path_resolver().app_log_dir())?;
+    info!("Recording requests to {}.", path.display());
+    Ok(path)
+}
+
+/// Stops recording requests and writes the recording.
+/// Returns the path of the recording, if one was in progress.
+#[tauri::command]
+pub fn har_stop(http: State<'_, HttpState>) -> Result<Option<PathBuf>, RecorderError> {
+    http.recorder.stop()
+}
+
+/// Returns the proxy for outbound connections, if any.
+#[tauri::command]
+pub fn proxy(http: State<'_, HttpState>) -> Option<ProxyConfig> {
+    http.proxy()
+}
+
+/// Sets or clears the proxy for outbound connections.
+#[tauri::command]
+pub fn set_proxy(http: State<'_, HttpState>, proxy: Option<ProxyConfig>) -> Result<(), HttpError> {
+    http.set_proxy(proxy)
+}
+
+/// Performs an HTTP request using the shared clients.
+/// Requests with an ID can be cancelled while in flight;
+/// only one request with a given ID may be in flight at a time.
+pub async fn send(http: &HttpState, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+    let request_id = match request.request_id.clone() {
+        Some(request_id) => request_id,
+        None => return send_cached(http, request).await
+    };
+
+    let (handle, registration) = AbortHandle::new_pair();
+    {
+        let mut in_flight = http.in_flight.lock().unwrap();
+        if in_flight.contains_key(&request_id) {
+            return Err(HttpError::DuplicateRequest(request_id));
+        }
+        in_flight.insert(request_id.clone(), handle.clone());
+    }
+
+    let result = Abortable::new(send_cached(http, request), registration).await;
+
+    // A cancelled request's handle was removed by `cancel`, and its ID may have been reused since.
+    {
+        let mut in_flight = http.in_flight.lock().unwrap();
+        if !handle.is_aborted() {
+            in_flight.remove(&request_id);
+        }
+    }
+
+    match result {
+        Ok(result) => result,
+        Err(Aborted) => Err(HttpError::Cancelled(request_id))
+    }
+}
+
+/// Answers the request from replay fixtures or the response cache if possible.
+/// Otherwise, sends it and caches the response if the route allows.
+async fn send_cached(http: &HttpState, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+    if let Some(replay) = &http.replay {
+        return replay.respond(&request).await?.into_response(&request);
+    }
+
+    // Replayed requests never reach the network, so only check the rest.
+    http.check(&request.url)?;
+
+    let policy = cache::policy(&request.method, &request.url);
+    if let Some(response) = http.cache.get(&request.method, &request.url, policy) {
+        return response.into_response(&request);
+    }
+
+    let response = match rate_limited_host(&request.url) {
+        Some(host) => send_limited(http, &request, &host).await?,
+        None => execute_recorded(http, &request).await?
+    };
+    if response.status == 200 {
+        http.cache.put(&request.method, &request.url, policy, &response);
+    }
+
+    response.into_response(&request)
+}
+
+/// Sends a request through the rate limiter of a host.
+/// The request's timeout also covers the time spent waiting for the limiter.
+async fn send_limited(http: &HttpState, request: &HttpRequest, host: &str) -> Result<RawResponse, HttpError> {
+    let timeout = match request.timeout_ms {
+        Some(timeout) => timeout,
+        None => return retry_limited(http, request, host).await
+    };
+
+    match tokio::time::timeout(Duration::from_millis(timeout), retry_limited(http, request, host)).await {
+        Ok(result) => result,
+        Err(_) => Err(HttpError::Timeout(format!(
+            "{} did not respond within {}ms, including time rate limited", request.url, timeout)))
+    }
+}
+
+/// Sends a request once the rate limiter allows.
+/// A 429 blocks the host for its `Retry-After` and the request is queued again.
+async fn retry_limited(http: &HttpState, request: &HttpRequest, host: &str) -> Result<RawResponse, HttpError> {
+    let mut retries = 0;
+    loop {
+        wait_for_limit(http, host).await;
+
+        let response = execute_recorded(http, request).await?;
+        if response.status != 429 || retries >= MAX_RATE_LIMIT_RETRIES {
+            return Ok(response);
+        }
+
+        let retry_after = limiter::parse_retry_after(response.header("Retry-After"));
+        warn!("Rate limited by {}; retrying in {:?}.", host, retry_after);
+
+        http.limiter.block(host, retry_after);
+        retries += 1;
+    }
+}
+
+/// Returns the host to rate limit requests to the given URL by.
+/// Requests to the local Riot Client are not limited.
+fn rate_limited_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    match url.host_str() {
+        None | Some("127.0.0.1") | Some("localhost") => None,
+        Some(host) => Some(host.to_string())
+    }
+}
+
+/// Counts a request as queued for a host until dropped.
+struct QueueGuard<'a> {
+    http: &'a HttpState,
+    host: &'a str
+}
+
+impl<'a> QueueGuard<'a> {
+    fn new(http: &'a HttpState, host: &'a str) -> Self {
+        let depth = http.limiter.enqueue(host);
+        http.emit("http-queue", QueueDepth { host: host.to_string(), depth });
+        Self { http, host }
+    }
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        let depth = self.http.limiter.dequeue(self.host);
+        self.http.emit("http-queue", QueueDepth { host: self.host.to_string(), depth });
+    }
+}
+
+/// Waits until the rate limiter allows a request to the host.
+async fn wait_for_limit(http: &HttpState, host: &str) {
+    let mut queued: Option<QueueGuard> = None;
+    while let Some(wait) = http.limiter.try_acquire(host) {
+        if queued.is_none() {
+            queued = Some(QueueGuard::new(http, host));
+        }
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Sends the request, adding it to the HAR recording if one is in progress.
+async fn execute_recorded(http: &HttpState, request: &HttpRequest) -> Result<RawResponse, HttpError> {
+    let started = Utc::now();
+    let timer = Instant::now();
+    let result = execute(http, request.clone()).await;
+
+    http.recorder.record(request, &result, started, timer.elapsed(), &http.secrets());
+    result
+}
+
+/// Sends the request and reads the response.
+async fn execute(http: &HttpState, request: HttpRequest) -> Result<RawResponse, HttpError> {
+    let url = match Url::parse(&request.url) {
+        Ok(url) => url,
+        Err(error) => return Err(HttpError::InvalidUrl(format!("{}: {}", request.url, error)))
+    };
+
+    let request_method = Method::from_str(&request.method);
+    if request_method.is_err() {
+        return Err(HttpError::InvalidMethod(request.method));
+    }
+    let request_method = request_method.unwrap();
+
+    let mut builder = http.client(&url, request.connect_timeout_ms)?.request(
+        request_method, url);
+
+    if let Some(timeout) = request.timeout_ms {
+        builder = builder.timeout(Duration::from_millis(timeout));
+    }
+
+    // Add all headers.
+    if let Some(headers) = request.headers {
+        for (key, value) in headers {
+            let name = match HeaderName::from_str(&key) {
+                Ok(name) => name,
+                Err(error) => return Err(HttpError::InvalidHeader(format!("{}: {}", key, error)))
+            };
+            let value = match HeaderValue::from_str(&value) {
+                Ok(value) => value,
+                Err(error) => return Err(HttpError::InvalidHeader(format!("{}: {}", key, error)))
+            };
+
+            builder = builder.header(name, value);
+        }
+    }
+
+    // Set the body if it exists.
+    if let Some(body) = request.body {
+        builder = builder.body(body.decode(request.body_encoding.unwrap_or_default())?);
+    }
+
+    let response = builder.send().await?;
+    let status = u16::from(response.status());
+    let headers = response.headers().iter()
+        .map(|(name, value)| HttpHeader::new(name, value))
+        .collect();
+
+    let body = match response.bytes().await {
+        Ok(body) => body.to_vec(),
+        Err(error) => return Err(HttpError::Decode(describe(&error)))
+    };
+
+    Ok(RawResponse { status, headers, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+    use crate::testing::{self, TestServer};
+
+    #[test]
+    fn connect_timeouts_are_rounded_up() {
+        assert_eq!(round_connect_timeout(None), None);
+        assert_eq!(round_connect_timeout(Some(0)), Some(250));
+        assert_eq!(round_connect_timeout(Some(1_000)), Some(1_000));
+        assert_eq!(round_connect_timeout(Some(1_001)), Some(2_000));
+        assert_eq!(round_connect_timeout(Some(30_000)), Some(30_000));
+        assert_eq!(round_connect_timeout(Some(30_001)), None);
+    }
+
+    #[tokio::test]
+    async fn connections_are_reused() {
+        let server = TestServer::start(Some(testing::tls_acceptor()),
+            |_| testing::response(200, &[], "ok")).await;
+        let http = HttpState::new(None).unwrap();
+        http.set_local_port(Some(server.port));
+        let url = format!("https://127.0.0.1:{}/", server.port);
+
+        for _ in 0..5 {
+            let response = send(&http, HttpRequest::new("GET", url.clone())).await.unwrap();
+            assert_eq!(response.status, 200);
+        }
+        assert_eq!(server.connections(), 1);
+
+        // These all round up to 2 seconds, so they share one more client.
+        for timeout in [1_200, 1_400, 1_600, 1_800, 2_000] {
+            let mut request = HttpRequest::new("GET", url.clone());
+            request.connect_timeout_ms = Some(timeout);
+            send(&http, request).await.unwrap();
+        }
+        assert_eq!(server.connections(), 2);
+        assert_eq!(server.requests().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn self_signed_certificates_are_only_accepted_locally() {
+        let local = TestServer::start(Some(testing::tls_acceptor()),
+            |_| testing::response(200, &[], "ok")).await;
+        let other = TestServer::start(Some(testing::tls_acceptor()),
+            |_| testing::response(200, &[], "ok")).await;
+        let http = HttpState::new(None).unwrap();
+        http.set_local_port(Some(local.port));
+
+        // `execute` skips the allowlist, so only certificate checks reject these.
+        let response = execute(&http, HttpRequest::new("GET", format!("https://127.0.0.1:{}/", local.port)))
+            .await.unwrap();
+        assert_eq!(response.status, 200);
+
+        for url in [
+            format!("https://127.0.0.1:{}/", other.port),
+            format!("https://localhost:{}/", local.port)
+        ] {
+            match execute(&http, HttpRequest::new("GET", url.clone())).await {
+                Err(HttpError::Tls(_)) => {}
+                result => panic!("expected a TLS error for {}, got {:?}", url, result)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn request_ids_are_unique_while_in_flight() {
+        // Accepts connections but never answers, so requests stay in flight until cancelled.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let http = HttpState::new(None).unwrap();
+        http.set_local_port(Some(port));
+        let request = |request_id: &str| {
+            let mut request = HttpRequest::new("GET", format!("https://127.0.0.1:{}/", port));
+            request.request_id = Some(request_id.to_string());
+            request
+        };
+
+        let first = send(&http, request("a"));
+        let rest = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let duplicate = send(&http, request("a")).await;
+            assert!(matches!(duplicate, Err(HttpError::DuplicateRequest(_))));
+            assert!(http.cancel("a"));
+
+            // The cancelled request must not remove the handle of a new request with its ID.
+            let reused = send(&http, request("a"));
+            let cancel = async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                assert!(http.cancel("a"));
+            };
+            let (reused, _) = tokio::join!(reused, cancel);
+            assert!(matches!(reused, Err(HttpError::Cancelled(_))));
+        };
+
+        let (first, _) = tokio::join!(first, rest);
+        assert!(matches!(first, Err(HttpError::Cancelled(_))));
+        assert!(http.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn every_header_value_is_kept() {
+        let server = TestServer::start(None, |_| b"HTTP/1.1 200 OK\r\n\
+            Set-Cookie: a=1\r\n\
+            Set-Cookie: b=2\r\n\
+            X-Name: caf\xe9\r\n\
+            Content-Length: 2\r\n\r\nok".to_vec()).await;
+        let http = HttpState::new(None).unwrap();
+
+        // `execute` skips the allowlist, which only allows HTTPS.
+        let response = execute(&http, HttpRequest::new("GET", format!("http://127.0.0.1:{}/", server.port)))
+            .await.unwrap();
+
+        let cookies: Vec<Option<&str>> = response.headers.iter()
+            .filter(|header| header.name == "set-cookie")
+            .map(|header| header.value.as_deref())
+            .collect();
+        assert_eq!(cookies, vec![Some("a=1"), Some("b=2")]);
+
+        let name = response.headers.iter().find(|header| header.name == "x-name").unwrap();
+        assert_eq!(name.value, None);
+        assert_eq!(name.raw.as_deref(), Some(&b"caf\xe9"[..]));
+        assert_eq!(response.header("X-Name"), None);
+        assert_eq!(serde_json::to_value(name).unwrap(), serde_json::json!({
+            "name": "x-name",
+            "value": null,
+            "raw": [99, 97, 102, 233]
+        }));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_are_retried() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let server = TestServer::start(None, move |_| match counter.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => testing::response(429, &[("Retry-After", "0")], ""),
+            _ => testing::response(200, &[], "ok")
+        }).await;
+        let http = HttpState::new(None).unwrap();
+        let request = HttpRequest::new("GET", format!("http://127.0.0.1:{}/", server.port));
+
+        // The local server stands in for a remote host.
+        let response = send_limited(&http, &request, "glz-na-1.na.a.pvp.net").await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_give_up() {
+        let server = TestServer::start(None,
+            |_| testing::response(429, &[("Retry-After", "0")], "")).await;
+        let http = HttpState::new(None).unwrap();
+        let request = HttpRequest::new("GET", format!("http://127.0.0.1:{}/", server.port));
+
+        let response = send_limited(&http, &request, "glz-na-1.na.a.pvp.net").await.unwrap();
+        assert_eq!(response.status, 429);
+        assert_eq!(server.requests().len(), MAX_RATE_LIMIT_RETRIES as usize + 1);
+    }
+
+    #[tokio::test]
+    async fn timeout_covers_rate_limit_waits() {
+        let server = TestServer::start(None,
+            |_| testing::response(429, &[("Retry-After", "5")], "")).await;
+        let http = HttpState::new(None).unwrap();
+        let mut request = HttpRequest::new("GET", format!("http://127.0.0.1:{}/", server.port));
+        request.timeout_ms = Some(300);
+
+        let started = Instant::now();
+        let result = send_limited(&http, &request, "glz-na-1.na.a.pvp.net").await;
+        assert!(matches!(result, Err(HttpError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(server.requests().len(), 1);
+
+        // The host stays blocked for the rest of its Retry-After.
+        assert!(http.limiter.try_acquire("glz-na-1.na.a.pvp.net").is_some());
+    }
+}
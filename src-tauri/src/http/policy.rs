@@ -0,0 +1,103 @@
+use reqwest::Url;
+use crate::http::HttpError;
+
+/// Remote hosts which may always be requested.
+const RIOT_HOSTS: [&str; 1] = ["*.a.pvp.net"];
+
+/// Checks a URL against the outbound allowlist.
+/// Allowed are the local Riot Client API (`127.0.0.1` on the lockfile port),
+/// Riot's game services (`*.a.pvp.net`), and the configured asset hosts.
+/// Only HTTPS is allowed.
+pub fn check(url: &Url, local_port: Option<u16>, asset_hosts: &[String]) -> Result<(), HttpError> {
+    if url.scheme() != "https" {
+        return Err(HttpError::Forbidden(format!("{} is not an HTTPS URL", url)));
+    }
+
+    let host = match url.host_str() {
+        Some(host) => host.to_ascii_lowercase(),
+        None => return Err(HttpError::Forbidden(format!("{} has no host", url)))
+    };
+
+    if host == "127.0.0.1" {
+        return match local_port {
+            Some(port) if url.port_or_known_default() == Some(port) => Ok(()),
+            _ => Err(HttpError::Forbidden(format!(
+                "{} is not the Riot Client API port", url.port_or_known_default().unwrap_or(0))))
+        };
+    }
+
+    let allowed = RIOT_HOSTS.iter().copied()
+        .chain(asset_hosts.iter().map(String::as_str))
+        .any(|pattern| host_matches(pattern, &host));
+    if allowed {
+        Ok(())
+    } else {
+        Err(HttpError::Forbidden(format!("{} is not an allowed host", host)))
+    }
+}
+
+/// Matches a host against a pattern.
+/// `*.example.com` matches any subdomain of `example.com`, but not `example.com` itself.
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.len() > domain.len() + 1
+            && host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.'),
+        None => host == pattern
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(url: &str, local_port: Option<u16>) -> bool {
+        let asset_hosts = vec!["valorant-api.com".to_string(), "*.valorant-api.com".to_string()];
+        check(&Url::parse(url).unwrap(), local_port, &asset_hosts).is_ok()
+    }
+
+    #[test]
+    fn host_matches_subdomains_only() {
+        assert!(host_matches("*.a.pvp.net", "pd.na.a.pvp.net"));
+        assert!(host_matches("*.a.pvp.net", "glz-na-1.na.a.pvp.net"));
+        assert!(!host_matches("*.a.pvp.net", "a.pvp.net"));
+        assert!(!host_matches("*.a.pvp.net", "evila.pvp.net"));
+        assert!(!host_matches("*.a.pvp.net", "pd.na.a.pvp.net.evil.com"));
+        assert!(host_matches("*.A.PVP.NET", "pd.na.a.pvp.net"));
+        assert!(host_matches("valorant-api.com", "valorant-api.com"));
+        assert!(!host_matches("valorant-api.com", "media.valorant-api.com"));
+    }
+
+    #[test]
+    fn check_allows_riot_hosts() {
+        assert!(allowed("https://pd.na.a.pvp.net/match-history/v1/history/puuid", None));
+        assert!(allowed("https://PD.NA.A.PVP.NET/", None));
+        assert!(!allowed("https://a.pvp.net/", None));
+        assert!(!allowed("https://evila.pvp.net/", None));
+        assert!(!allowed("https://example.com/", None));
+    }
+
+    #[test]
+    fn check_allows_asset_hosts() {
+        assert!(allowed("https://valorant-api.com/v1/agents", None));
+        assert!(allowed("https://media.valorant-api.com/agents/icon.png", None));
+        assert!(!allowed("https://valorant-api.com.evil.com/", None));
+    }
+
+    #[test]
+    fn check_requires_https() {
+        assert!(!allowed("http://pd.na.a.pvp.net/", None));
+        assert!(!allowed("http://127.0.0.1:1234/", Some(1234)));
+        assert!(!allowed("wss://127.0.0.1:1234/", Some(1234)));
+    }
+
+    #[test]
+    fn check_allows_only_the_lockfile_port_locally() {
+        assert!(allowed("https://127.0.0.1:1234/help", Some(1234)));
+        assert!(!allowed("https://127.0.0.1:4321/help", Some(1234)));
+        assert!(!allowed("https://127.0.0.1/help", Some(1234)));
+        assert!(!allowed("https://127.0.0.1:1234/help", None));
+        assert!(!allowed("https://localhost:1234/help", Some(1234)));
+    }
+}
@@ -0,0 +1,107 @@
+use log::warn;
+use reqwest::{Proxy, Url};
+use serde::{Deserialize, Serialize};
+use crate::http::HttpError;
+use crate::http::policy::host_matches;
+
+/// Sets the proxy on startup, e.g. `socks5://127.0.0.1:1080`.
+const PROXY_ENV: &str = "GUILDED_STATS_PROXY";
+/// Comma-separated hosts which bypass the proxy.
+const NO_PROXY_ENV: &str = "GUILDED_STATS_NO_PROXY";
+
+/// An HTTP or SOCKS5 proxy for outbound requests.
+/// The local Riot Client, including its websocket, is always connected to directly.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    /// `http://`, `socks5://` or `socks5h://`, with optional credentials.
+    pub url: String,
+    /// Hosts which are connected to directly. `*.example.com` matches subdomains.
+    /// The local Riot Client (`127.0.0.1`) is always bypassed.
+    #[serde(default)]
+    pub bypass: Vec<String>
+}
+
+impl ProxyConfig {
+    /// Reads the proxy from the environment, if set.
+    /// An invalid proxy is logged and ignored.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var(PROXY_ENV).ok()?;
+        let bypass = std::env::var(NO_PROXY_ENV).unwrap_or_default()
+            .split(',')
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .collect();
+
+        let proxy = Self { url, bypass };
+        match proxy.validate() {
+            Ok(_) => Some(proxy),
+            Err(error) => {
+                warn!("Ignoring {}: {}", PROXY_ENV, error);
+                None
+            }
+        }
+    }
+
+    /// Parses and checks the proxy URL.
+    pub fn validate(&self) -> Result<Url, HttpError> {
+        let url = match Url::parse(&self.url) {
+            Ok(url) => url,
+            Err(error) => return Err(HttpError::InvalidUrl(format!("proxy {}: {}", self.url, error)))
+        };
+
+        match url.scheme() {
+            "http" | "socks5" | "socks5h" if url.host_str().is_some() => Ok(url),
+            scheme => Err(HttpError::InvalidUrl(format!("unsupported proxy scheme: {}", scheme)))
+        }
+    }
+
+    /// Whether connections to the host skip the proxy.
+    pub fn bypasses(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        host == "127.0.0.1" || host == "localhost"
+            || self.bypass.iter().any(|pattern| host_matches(pattern, &host))
+    }
+
+    /// Creates the proxy for a reqwest client.
+    pub fn reqwest_proxy(&self) -> Result<Proxy, HttpError> {
+        let proxy = self.validate()?;
+        let config = self.clone();
+        Ok(Proxy::custom(move |url| match url.host_str() {
+            Some(host) if !config.bypasses(host) => Some(proxy.clone()),
+            _ => None
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_env_ignores_invalid_proxies() {
+        // The only test which reads these variables.
+        std::env::set_var(PROXY_ENV, "ftp://proxy.example.com");
+        assert_eq!(ProxyConfig::from_env(), None);
+
+        std::env::set_var(PROXY_ENV, "socks5://127.0.0.1:1080");
+        std::env::set_var(NO_PROXY_ENV, "example.com, *.riotgames.com");
+        assert_eq!(ProxyConfig::from_env(), Some(ProxyConfig {
+            url: "socks5://127.0.0.1:1080".to_string(),
+            bypass: vec!["example.com".to_string(), "*.riotgames.com".to_string()]
+        }));
+
+        std::env::remove_var(PROXY_ENV);
+        std::env::remove_var(NO_PROXY_ENV);
+        assert_eq!(ProxyConfig::from_env(), None);
+    }
+
+    #[test]
+    fn local_hosts_are_always_bypassed() {
+        let proxy = ProxyConfig { url: "http://proxy:8080".to_string(), bypass: vec!["*.riotgames.com".to_string()] };
+        assert!(proxy.bypasses("127.0.0.1"));
+        assert!(proxy.bypasses("LOCALHOST"));
+        assert!(proxy.bypasses("auth.riotgames.com"));
+        assert!(!proxy.bypasses("pd.na.a.pvp.net"));
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use base64::prelude::*;
+use log::{info, warn};
+use rand::Rng;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::http::{HttpError, HttpHeader, HttpRequest, RawResponse};
+use crate::lockfile::{self, LockfileError, LockfileInfo};
+
+/// Enables replay mode with a directory of fixtures.
+const REPLAY_ENV: &str = "GUILDED_STATS_REPLAY";
+/// Delay added to every replayed response, in milliseconds.
+const LATENCY_ENV: &str = "GUILDED_STATS_REPLAY_LATENCY_MS";
+/// Fraction of replayed requests which fail, from 0 to 1.
+const ERROR_RATE_ENV: &str = "GUILDED_STATS_REPLAY_ERROR_RATE";
+/// The region the remote API is set up for.
+const REGION_ENV: &str = "GUILDED_STATS_REPLAY_REGION";
+/// The shard the remote API is set up for.
+const SHARD_ENV: &str = "GUILDED_STATS_REPLAY_SHARD";
+/// Used when the fixture directory has no `lockfile`.
+/// Requests are answered from fixtures, so the port is never connected to.
+const PLACEHOLDER_LOCKFILE: &str = "Riot Client:0:65535:replay:https";
+
+/// How replay mode was configured.
+#[derive(Clone, Debug)]
+pub struct ReplayConfig {
+    /// The directory fixtures are read from.
+    pub dir: PathBuf,
+    pub latency: Duration,
+    pub error_rate: f64,
+    pub region: String,
+    pub shard: String
+}
+
+impl ReplayConfig {
+    /// Reads the configuration from command line flags, falling back to environment variables.
+    /// Flags: `--replay <dir>`, `--replay-latency <ms>`, `--replay-error-rate <0..1>`,
+    /// `--replay-region <region>` and `--replay-shard <shard>`; both default to `na`.
+    /// Returns none if replay mode is not enabled.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let flag = |name: &str, env: &str| args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1).cloned())
+            .or_else(|| std::env::var(env).ok());
+
+        let dir = flag("--replay", REPLAY_ENV)?;
+        let latency = flag("--replay-latency", LATENCY_ENV)
+            .and_then(|latency| latency.parse::<u64>().ok())
+            .unwrap_or(0);
+        let error_rate = flag("--replay-error-rate", ERROR_RATE_ENV)
+            .and_then(|rate| rate.parse::<f64>().ok())
+            .unwrap_or(0.0);
+
+        Some(Self {
+            dir: PathBuf::from(dir),
+            latency: Duration::from_millis(latency),
+            error_rate: error_rate.clamp(0.0, 1.0),
+            region: flag("--replay-region", REGION_ENV).unwrap_or_else(|| "na".to_string()),
+            shard: flag("--replay-shard", SHARD_ENV).unwrap_or_else(|| "na".to_string())
+        })
+    }
+
+    /// Returns the lockfile to use instead of the Riot Client's:
+    /// `lockfile` in the fixture directory, or a placeholder.
+    /// Its process is not checked, so VALORANT does not need to be running.
+    pub fn lockfile(&self) -> LockfileInfo {
+        let path = self.dir.join("lockfile");
+        if path.exists() {
+            let info = fs::read_to_string(&path)
+                .map_err(|error| LockfileError::Unreadable(error.to_string()))
+                .and_then(|text| lockfile::parse(&text));
+            match info {
+                Ok(info) => return info,
+                Err(error) => warn!("Ignoring {}: {}", path.display(), error)
+            }
+        }
+
+        lockfile::parse(PLACEHOLDER_LOCKFILE).unwrap()
+    }
+}
+
+/// A response fixture in the JSON format.
+/// A fixture file may contain one fixture or an array of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonFixtures {
+    One(JsonFixture),
+    Many(Vec<JsonFixture>)
+}
+
+#[derive(Deserialize)]
+struct JsonFixture {
+    method: String,
+    /// The URL path, without the host.
+    path: String,
+    #[serde(default = "default_status")]
+    status: u16,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    /// A string is returned as-is; anything else is returned as JSON.
+    #[serde(default)]
+    body: Value
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// Answers requests from recorded fixtures instead of the network.
+pub struct Replay {
+    config: ReplayConfig,
+    /// Responses keyed by method and URL path.
+    fixtures: Mutex<HashMap<(String, String), RawResponse>>
+}
+
+impl Replay {
+    pub fn new(config: ReplayConfig) -> Self {
+        Self {
+            config,
+            fixtures: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Loads every `.har` and `.json` fixture in the configured directory.
+    /// Unreadable fixtures are skipped.
+    pub fn load(&self) {
+        let config = &self.config;
+        let mut fixtures = HashMap::new();
+
+        let files: Vec<PathBuf> = match fs::read_dir(&config.dir) {
+            Ok(files) => files.filter_map(|file| file.ok()).map(|file| file.path()).collect(),
+            Err(error) => {
+                warn!("Unable to read replay fixtures from {}: {}", config.dir.display(), error);
+                Vec::new()
+            }
+        };
+
+        for path in files {
+            let result = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("har") => load_har(&path, &mut fixtures),
+                Some("json") => load_json(&path, &mut fixtures),
+                _ => continue
+            };
+
+            if let Err(error) = result {
+                warn!("Skipping replay fixture {}: {}", path.display(), error);
+            }
+        }
+
+        info!("Replaying {} responses from {}.", fixtures.len(), config.dir.display());
+        *self.fixtures.lock().unwrap() = fixtures;
+    }
+
+    /// Answers a request from the fixtures.
+    /// Requests without a fixture receive a 404.
+    pub async fn respond(&self, request: &HttpRequest) -> Result<RawResponse, HttpError> {
+        if !self.config.latency.is_zero() {
+            tokio::time::sleep(self.config.latency).await;
+        }
+
+        if self.config.error_rate > 0.0 && rand::thread_rng().gen::<f64>() < self.config.error_rate {
+            return Err(HttpError::Connect(format!("injected error for {}", request.url)));
+        }
+
+        let key = match fixture_key(&request.method, &request.url) {
+            Some(key) => key,
+            None => return Err(HttpError::InvalidUrl(request.url.clone()))
+        };
+
+        let response = self.fixtures.lock().unwrap().get(&key).cloned();
+        match response {
+            Some(response) => Ok(response),
+            None => {
+                warn!("No replay fixture for {} {}.", key.0, key.1);
+                Ok(RawResponse {
+                    status: 404,
+                    headers: Vec::new(),
+                    body: format!("No replay fixture for {} {}", key.0, key.1).into_bytes()
+                })
+            }
+        }
+    }
+}
+
+/// Returns the key of a fixture: the method and the URL path.
+fn fixture_key(method: &str, url: &str) -> Option<(String, String)> {
+    let url = Url::parse(url).ok()?;
+    Some((method.to_uppercase(), url.path().to_string()))
+}
+
+fn header(name: &str, value: &str) -> HttpHeader {
+    HttpHeader {
+        name: name.to_string(),
+        value: Some(value.to_string()),
+        raw: None
+    }
+}
+
+/// Loads the responses of a HAR recording.
+/// Later entries for the same request replace earlier ones.
+fn load_har(path: &Path, fixtures: &mut HashMap<(String, String), RawResponse>) -> Result<(), String> {
+    let text = fs::read(path).map_err(|error| error.to_string())?;
+    let har: Value = serde_json::from_slice(&text).map_err(|error| error.to_string())?;
+    let entries = match har["log"]["entries"].as_array() {
+        Some(entries) => entries,
+        None => return Err("missing log.entries".to_string())
+    };
+
+    for entry in entries {
+        let (request, response) = (&entry["request"], &entry["response"]);
+        let status = response["status"].as_u64().unwrap_or(0) as u16;
+        let key = fixture_key(
+            request["method"].as_str().unwrap_or("GET"),
+            request["url"].as_str().unwrap_or(""));
+        let key = match key {
+            Some(key) if status != 0 => key,
+            _ => continue
+        };
+
+        let headers = response["headers"].as_array().into_iter().flatten()
+            .map(|entry| header(
+                entry["name"].as_str().unwrap_or(""),
+                entry["value"].as_str().unwrap_or("")))
+            .collect();
+
+        let text = response["content"]["text"].as_str().unwrap_or("");
+        let body = if response["content"]["encoding"] == "base64" {
+            BASE64_STANDARD.decode(text).map_err(|error| error.to_string())?
+        } else {
+            text.as_bytes().to_vec()
+        };
+
+        fixtures.insert(key, RawResponse { status, headers, body });
+    }
+
+    Ok(())
+}
+
+/// Loads one or more JSON fixtures.
+fn load_json(path: &Path, fixtures: &mut HashMap<(String, String), RawResponse>) -> Result<(), String> {
+    let text = fs::read(path).map_err(|error| error.to_string())?;
+    let loaded = match serde_json::from_slice(&text) {
+        Ok(JsonFixtures::One(fixture)) => vec![fixture],
+        Ok(JsonFixtures::Many(fixtures)) => fixtures,
+        Err(error) => return Err(error.to_string())
+    };
+
+    for fixture in loaded {
+        let headers = fixture.headers.iter()
+            .map(|(name, value)| header(name, value))
+            .collect();
+        let body = match fixture.body {
+            Value::String(text) => text.into_bytes(),
+            Value::Null => Vec::new(),
+            body => body.to_string().into_bytes()
+        };
+
+        fixtures.insert(
+            (fixture.method.to_uppercase(), fixture.path),
+            RawResponse { status: fixture.status, headers, body });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    /// Creates an empty temporary directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("guilded-stats-replay-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn config(dir: PathBuf) -> ReplayConfig {
+        ReplayConfig {
+            dir,
+            latency: Duration::ZERO,
+            error_rate: 0.0,
+            region: "na".to_string(),
+            shard: "na".to_string()
+        }
+    }
+
+    fn key(method: &str, path: &str) -> (String, String) {
+        (method.to_string(), path.to_string())
+    }
+
+    #[test]
+    fn from_args_reads_flags() {
+        let config = ReplayConfig::from_args(&args(&[
+            "guilded-stats", "--replay", "fixtures",
+            "--replay-latency", "250", "--replay-error-rate", "2",
+            "--replay-region", "eu", "--replay-shard", "pbe"
+        ])).unwrap();
+
+        assert_eq!(config.dir, PathBuf::from("fixtures"));
+        assert_eq!(config.latency, Duration::from_millis(250));
+        assert_eq!(config.error_rate, 1.0);
+        assert_eq!((config.region.as_str(), config.shard.as_str()), ("eu", "pbe"));
+    }
+
+    #[test]
+    fn from_args_uses_defaults() {
+        let config = ReplayConfig::from_args(&args(&[
+            "guilded-stats", "--replay", "fixtures", "--replay-latency", "soon"
+        ])).unwrap();
+
+        assert_eq!(config.latency, Duration::ZERO);
+        assert_eq!(config.error_rate, 0.0);
+        assert_eq!((config.region.as_str(), config.shard.as_str()), ("na", "na"));
+
+        // The directory is required.
+        if std::env::var(REPLAY_ENV).is_err() {
+            assert!(ReplayConfig::from_args(&args(&["guilded-stats", "--replay"])).is_none());
+            assert!(ReplayConfig::from_args(&args(&["guilded-stats"])).is_none());
+        }
+    }
+
+    #[test]
+    fn fixture_key_uses_the_method_and_path() {
+        assert_eq!(fixture_key("get", "https://pd.na.a.pvp.net/match-history/v1/history/puuid?startIndex=0"),
+            Some(key("GET", "/match-history/v1/history/puuid")));
+        assert_eq!(fixture_key("GET", "https://127.0.0.1:1234/chat/v1/session"),
+            Some(key("GET", "/chat/v1/session")));
+        assert_eq!(fixture_key("GET", "/chat/v1/session"), None);
+    }
+
+    #[test]
+    fn load_har_reads_every_entry() {
+        let dir = temp_dir("har");
+        let path = dir.join("recording.har");
+        let har = json!({ "log": { "entries": [
+            {
+                "request": { "method": "GET", "url": "https://127.0.0.1:1234/chat/v1/session" },
+                "response": {
+                    "status": 500,
+                    "headers": [],
+                    "content": { "text": "old" }
+                }
+            },
+            {
+                "request": { "method": "GET", "url": "https://127.0.0.1:1234/chat/v1/session" },
+                "response": {
+                    "status": 200,
+                    "headers": [{ "name": "Content-Type", "value": "application/json" }],
+                    "content": { "text": "{}" }
+                }
+            },
+            {
+                "request": { "method": "GET", "url": "https://pd.na.a.pvp.net/bytes" },
+                "response": {
+                    "status": 200,
+                    "headers": [],
+                    "content": { "text": "AAEC", "encoding": "base64" }
+                }
+            },
+            {
+                "request": { "method": "GET", "url": "https://pd.na.a.pvp.net/failed" },
+                "response": { "status": 0 }
+            }
+        ]}});
+        fs::write(&path, har.to_string()).unwrap();
+
+        let mut fixtures = HashMap::new();
+        load_har(&path, &mut fixtures).unwrap();
+        fs::write(&path, "{}").unwrap();
+        let missing = load_har(&path, &mut HashMap::new());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(fixtures.len(), 2);
+        let session = &fixtures[&key("GET", "/chat/v1/session")];
+        assert_eq!(session.status, 200);
+        assert_eq!(session.header("content-type"), Some("application/json"));
+        assert_eq!(session.body, b"{}");
+        assert_eq!(fixtures[&key("GET", "/bytes")].body, vec![0, 1, 2]);
+        assert_eq!(missing, Err("missing log.entries".to_string()));
+    }
+
+    #[test]
+    fn load_json_reads_one_or_many_fixtures() {
+        let dir = temp_dir("json");
+        let one = dir.join("one.json");
+        let many = dir.join("many.json");
+        fs::write(&one, json!({
+            "method": "get",
+            "path": "/entitlements/v1/token",
+            "body": { "accessToken": "access" }
+        }).to_string()).unwrap();
+        fs::write(&many, json!([
+            { "method": "GET", "path": "/text", "status": 201, "headers": { "X-Test": "1" }, "body": "plain" },
+            { "method": "DELETE", "path": "/empty", "status": 204 }
+        ]).to_string()).unwrap();
+
+        let mut fixtures = HashMap::new();
+        load_json(&one, &mut fixtures).unwrap();
+        load_json(&many, &mut fixtures).unwrap();
+        fs::write(&one, "not json").unwrap();
+        let invalid = load_json(&one, &mut HashMap::new());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(fixtures.len(), 3);
+        let token = &fixtures[&key("GET", "/entitlements/v1/token")];
+        assert_eq!(token.status, 200);
+        assert_eq!(token.body, br#"{"accessToken":"access"}"#);
+        let text = &fixtures[&key("GET", "/text")];
+        assert_eq!((text.status, text.header("X-Test"), text.body.as_slice()), (201, Some("1"), &b"plain"[..]));
+        assert!(fixtures[&key("DELETE", "/empty")].body.is_empty());
+        assert!(invalid.is_err());
+    }
+
+    #[tokio::test]
+    async fn respond_answers_from_fixtures() {
+        let dir = temp_dir("respond");
+        fs::write(dir.join("session.json"), json!({
+            "method": "GET", "path": "/chat/v1/session", "body": { "puuid": "puuid" }
+        }).to_string()).unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let replay = Replay::new(config(dir.clone()));
+        replay.load();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let request = HttpRequest::new("GET", "https://127.0.0.1:1234/chat/v1/session");
+        let response = replay.respond(&request).await.unwrap();
+        assert_eq!((response.status, response.body), (200, br#"{"puuid":"puuid"}"#.to_vec()));
+
+        let request = HttpRequest::new("GET", "https://127.0.0.1:1234/help");
+        assert_eq!(replay.respond(&request).await.unwrap().status, 404);
+    }
+
+    #[test]
+    fn lockfile_is_read_from_the_fixtures() {
+        let dir = temp_dir("lockfile");
+        let config = config(dir.clone());
+        assert_eq!(config.lockfile(), lockfile::parse(PLACEHOLDER_LOCKFILE).unwrap());
+
+        // The process does not need to exist.
+        fs::write(dir.join("lockfile"), "Riot Client:4294967295:51234:secret:https").unwrap();
+        let info = config.lockfile();
+        assert_eq!((info.pid, info.port, info.password.as_str()), (4294967295, 51234, "secret"));
+
+        fs::write(dir.join("lockfile"), "Riot Client").unwrap();
+        assert_eq!(config.lockfile().port, 65535);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
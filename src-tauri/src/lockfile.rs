@@ -0,0 +1,274 @@
+use std::{fmt, fs, thread};
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use log::{info, warn};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use sysinfo::{Pid, System};
+use tauri::{AppHandle, Manager, State};
+use crate::http::HttpState;
+
+/// Connection information for the local Riot Client API.
+/// Read from `Riot Client/Config/lockfile`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LockfileInfo {
+    pub name: String,
+    pub pid: u32,
+    pub port: u16,
+    pub password: String,
+    pub protocol: String
+}
+
+/// The lockfile of the currently running Riot Client, if any.
+/// Kept up to date by `watch`.
+#[derive(Default)]
+pub struct LockfileState(pub Mutex<Option<LockfileInfo>>);
+
+impl LockfileState {
+    /// Returns the current lockfile.
+    /// Falls back to reading it if the watcher has not seen it yet.
+    pub fn current(&self) -> Result<LockfileInfo, LockfileError> {
+        let info = self.0.lock().unwrap().clone();
+        match info {
+            Some(info) => Ok(info),
+            None => read()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum LockfileError {
+    /// The local data directory could not be resolved.
+    NoDataDir,
+    /// The lockfile does not exist. The Riot Client is not running.
+    NotFound,
+    /// The lockfile exists but could not be read.
+    Unreadable(String),
+    /// The lockfile is not in the `name:pid:port:password:protocol` format.
+    Malformed(String),
+    /// The process which wrote the lockfile is no longer running.
+    Stale(u32)
+}
+
+impl fmt::Display for LockfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockfileError::NoDataDir => write!(f, "Unable to resolve the local data directory."),
+            LockfileError::NotFound => write!(f, "'lockfile' does not exist. Is VALORANT open?"),
+            LockfileError::Unreadable(error) => write!(f, "Unable to read 'lockfile': {}", error),
+            LockfileError::Malformed(error) => write!(f, "Malformed 'lockfile': {}", error),
+            LockfileError::Stale(pid) => write!(f, "'lockfile' belongs to process {}, which is not running.", pid)
+        }
+    }
+}
+
+/// Returns the path to the Riot Client lockfile.
+pub fn path() -> Option<PathBuf> {
+    tauri::api::path::local_data_dir().map(|dir| dir
+        .join("Riot Games")
+        .join("Riot Client")
+        .join("Config")
+        .join("lockfile"))
+}
+
+/// Parses the contents of a lockfile.
+/// This does not check if the process is still alive.
+pub fn parse(text: &str) -> Result<LockfileInfo, LockfileError> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() != 5 {
+        return Err(LockfileError::Malformed(
+            format!("expected 5 fields, found {}", parts.len())));
+    }
+
+    let pid = parts[1].parse::<u32>();
+    if pid.is_err() {
+        return Err(LockfileError::Malformed(format!("invalid pid '{}'", parts[1])));
+    }
+
+    let port = parts[2].parse::<u16>().unwrap_or(0);
+    if port == 0 {
+        return Err(LockfileError::Malformed(format!("invalid port '{}'", parts[2])));
+    }
+
+    if parts[3].is_empty() {
+        return Err(LockfileError::Malformed("empty password".to_string()));
+    }
+
+    let protocol = parts[4];
+    if protocol != "http" && protocol != "https" {
+        return Err(LockfileError::Malformed(format!("invalid protocol '{}'", protocol)));
+    }
+
+    Ok(LockfileInfo {
+        name: parts[0].to_string(),
+        pid: pid.unwrap(),
+        port,
+        password: parts[3].to_string(),
+        protocol: protocol.to_string()
+    })
+}
+
+/// Reads and validates the lockfile.
+pub fn read() -> Result<LockfileInfo, LockfileError> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Err(LockfileError::NoDataDir)
+    };
+    if !path.exists() {
+        return Err(LockfileError::NotFound);
+    }
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) => return Err(LockfileError::Unreadable(error.to_string()))
+    };
+
+    let info = parse(&text)?;
+    if !is_process_alive(info.pid) {
+        return Err(LockfileError::Stale(info.pid));
+    }
+
+    Ok(info)
+}
+
+/// Checks if a process with the given ID is running.
+fn is_process_alive(pid: u32) -> bool {
+    let mut system = System::new();
+    system.refresh_process(Pid::from_u32(pid))
+}
+
+/// Watches the lockfile for changes on a background thread.
+/// Emits `riot-client-started` and `riot-client-stopped` to the webview.
+pub fn watch(app: AppHandle) {
+    let path = match path() {
+        Some(path) => path,
+        None => {
+            warn!("Unable to resolve the lockfile path; not watching for the Riot Client.");
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        // The directory is created by the Riot Client on first launch.
+        let directory = path.parent().unwrap().to_path_buf();
+        while !directory.exists() {
+            thread::sleep(Duration::from_secs(5));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender);
+        if let Err(error) = watcher {
+            warn!("Unable to create lockfile watcher: {}", error);
+            return;
+        }
+        let mut watcher = watcher.unwrap();
+
+        // The lockfile is replaced when the client restarts, so watch its directory.
+        if let Err(error) = watcher.watch(&directory, RecursiveMode::NonRecursive) {
+            warn!("Unable to watch {}: {}", directory.display(), error);
+            return;
+        }
+
+        // The client may already be running.
+        refresh(&app);
+
+        for event in receiver {
+            let event = match event {
+                Ok(event) => event,
+                Err(error) => {
+                    warn!("Lockfile watcher error: {}", error);
+                    continue;
+                }
+            };
+
+            if !event.paths.iter().any(|changed| changed.file_name() == path.file_name()) {
+                continue;
+            }
+
+            match event.kind {
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => refresh(&app),
+                _ => {}
+            }
+        }
+    });
+}
+
+/// Uses the given lockfile instead of watching for the Riot Client, e.g. in replay mode.
+pub fn pin(app: &AppHandle, info: LockfileInfo) {
+    info!("Using a fixed lockfile for port {}.", info.port);
+    update(app, Some(info));
+}
+
+/// Re-reads the lockfile and emits an event if the client changed.
+fn refresh(app: &AppHandle) {
+    match read() {
+        Ok(info) => update(app, Some(info)),
+        // The client may still be writing the file; wait for the next event.
+        Err(LockfileError::Malformed(_)) | Err(LockfileError::Unreadable(_)) => {}
+        Err(_) => update(app, None)
+    }
+}
+
+/// Replaces the known lockfile and notifies the webview.
+fn update(app: &AppHandle, info: Option<LockfileInfo>) {
+    let state = app.state::<LockfileState>();
+    let mut current = state.0.lock().unwrap();
+    if *current == info {
+        return;
+    }
+
+    if let Some(previous) = current.take() {
+        info!("Riot Client (pid {}) has stopped.", previous.pid);
+        app.emit_all("riot-client-stopped", previous).unwrap();
+    }
+    if let Some(info) = &info {
+        info!("Riot Client (pid {}) has started on port {}.", info.pid, info.port);
+        app.emit_all("riot-client-started", info).unwrap();
+    }
+
+    app.state::<HttpState>().set_local_port(info.as_ref().map(|info| info.port));
+    *current = info;
+}
+
+#[tauri::command]
+pub fn lockfile(state: State<'_, LockfileState>) -> Result<LockfileInfo, LockfileError> {
+    state.current()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn malformed(text: &str) -> String {
+        match parse(text) {
+            Err(LockfileError::Malformed(error)) => error,
+            result => panic!("expected a malformed lockfile, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn parse_reads_every_field() {
+        let info = parse("Riot Client:1234:51234:secret:https\n").unwrap();
+        assert_eq!(info, LockfileInfo {
+            name: "Riot Client".to_string(),
+            pid: 1234,
+            port: 51234,
+            password: "secret".to_string(),
+            protocol: "https".to_string()
+        });
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lockfiles() {
+        assert_eq!(malformed("Riot Client:1234:51234:secret"), "expected 5 fields, found 4");
+        assert_eq!(malformed("Riot Client:1234:51234:secret:https:extra"), "expected 5 fields, found 6");
+        assert_eq!(malformed("Riot Client:abc:51234:secret:https"), "invalid pid 'abc'");
+        assert_eq!(malformed("Riot Client:1234:0:secret:https"), "invalid port '0'");
+        assert_eq!(malformed("Riot Client:1234:65536:secret:https"), "invalid port '65536'");
+        assert_eq!(malformed("Riot Client:1234:port:secret:https"), "invalid port 'port'");
+        assert_eq!(malformed("Riot Client:1234:51234::https"), "empty password");
+        assert_eq!(malformed("Riot Client:1234:51234:secret:wss"), "invalid protocol 'wss'");
+    }
+}
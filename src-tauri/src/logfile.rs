@@ -0,0 +1,270 @@
+use std::{fs, thread};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use log::warn;
+use regex::Regex;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::game::{self, Signal};
+
+/// How often the log file is checked for new lines.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Game information gathered from `ShooterGame.log`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GameLogInfo {
+    pub region: Option<String>,
+    pub shard: Option<String>,
+    pub version: Option<String>,
+    pub pregame_id: Option<String>,
+    pub match_id: Option<String>
+}
+
+#[derive(Default)]
+pub struct GameLogState(pub Mutex<GameLogInfo>);
+
+/// An event extracted from a line of `ShooterGame.log`.
+/// Emitted to the webview as `game-log`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogEvent {
+    Region { region: String, shard: String },
+    Version { version: String },
+    Pregame { match_id: String },
+    CoreGame { match_id: String }
+}
+
+impl GameLogInfo {
+    /// Applies an event to the known game information.
+    /// Returns false if the event did not change anything.
+    fn apply(&mut self, event: &LogEvent) -> bool {
+        match event {
+            LogEvent::Region { region, shard } => {
+                if self.region.as_ref() == Some(region) && self.shard.as_ref() == Some(shard) {
+                    return false;
+                }
+                self.region = Some(region.clone());
+                self.shard = Some(shard.clone());
+            }
+            LogEvent::Version { version } => {
+                if self.version.as_ref() == Some(version) {
+                    return false;
+                }
+                self.version = Some(version.clone());
+            }
+            LogEvent::Pregame { match_id } => {
+                if self.pregame_id.as_ref() == Some(match_id) {
+                    return false;
+                }
+                self.pregame_id = Some(match_id.clone());
+            }
+            LogEvent::CoreGame { match_id } => {
+                if self.match_id.as_ref() == Some(match_id) {
+                    return false;
+                }
+                self.match_id = Some(match_id.clone());
+            }
+        }
+
+        true
+    }
+}
+
+struct LogParser {
+    glz: Regex,
+    version: Regex,
+    match_id: Regex
+}
+
+impl LogParser {
+    fn new() -> Self {
+        Self {
+            glz: Regex::new(r"https://glz-([a-z0-9]+)-1\.([a-z0-9]+)\.a\.pvp\.net").unwrap(),
+            version: Regex::new(r"CI server version: (release-[\w.\-]+)").unwrap(),
+            match_id: Regex::new(r"/(pregame|core-game)/v1/matches/([0-9a-f\-]{36})").unwrap()
+        }
+    }
+
+    /// Extracts an event from a single log line.
+    fn parse(&self, line: &str) -> Option<LogEvent> {
+        if let Some(captures) = self.match_id.captures(line) {
+            let match_id = captures[2].to_string();
+            return Some(match &captures[1] {
+                "pregame" => LogEvent::Pregame { match_id },
+                _ => LogEvent::CoreGame { match_id }
+            });
+        }
+
+        if let Some(captures) = self.glz.captures(line) {
+            return Some(LogEvent::Region {
+                region: captures[1].to_string(),
+                shard: captures[2].to_string()
+            });
+        }
+
+        if let Some(captures) = self.version.captures(line) {
+            return Some(LogEvent::Version {
+                version: captures[1].to_string()
+            });
+        }
+
+        None
+    }
+}
+
+/// Returns the path to the VALORANT game log.
+pub fn path() -> Option<PathBuf> {
+    tauri::api::path::local_data_dir().map(|dir| dir
+        .join("VALORANT")
+        .join("Saved")
+        .join("Logs")
+        .join("ShooterGame.log"))
+}
+
+/// Tails the game log on a background thread.
+/// Emits `game-log` for every change in game information,
+/// and `game-log-reset` when the game starts a new log.
+pub fn tail(app: AppHandle) {
+    let path = match path() {
+        Some(path) => path,
+        None => {
+            warn!("Unable to resolve the game log path; not tailing 'ShooterGame.log'.");
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        let parser = LogParser::new();
+        let mut offset = 0u64;
+        let mut pending: Vec<u8> = Vec::new();
+        // Lines already in the log at startup are from earlier matches. They fill
+        // in the game information, but only later lines move the game phase.
+        let mut caught_up = false;
+
+        loop {
+            let length = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+
+            // The game truncates the log when it launches.
+            if length < offset {
+                offset = 0;
+                pending.clear();
+
+                *app.state::<GameLogState>().0.lock().unwrap() = GameLogInfo::default();
+                app.emit_all("game-log-reset", ()).unwrap();
+                game::apply(&app, Signal::Reset);
+            }
+
+            if length > offset {
+                match read_from(&path, offset) {
+                    Ok(bytes) => {
+                        offset += bytes.len() as u64;
+                        pending.extend_from_slice(&bytes);
+                    }
+                    Err(error) => warn!("Unable to read 'ShooterGame.log': {}", error)
+                }
+
+                // Only complete lines are parsed; the rest waits for the next read.
+                while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    if let Some(event) = parser.parse(&String::from_utf8_lossy(&line)) {
+                        publish(&app, event, caught_up);
+                    }
+                }
+            }
+            caught_up = true;
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Sets the region and shard instead of tailing the game log, e.g. in replay mode.
+pub fn seed(app: &AppHandle, region: String, shard: String) {
+    let state = app.state::<GameLogState>();
+    let mut info = state.0.lock().unwrap();
+    info.region = Some(region);
+    info.shard = Some(shard);
+}
+
+/// Reads the file from the given offset to its end.
+fn read_from(path: &Path, offset: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Records an event and notifies the webview if it changed anything.
+/// New pre-game and match IDs are passed on to the game phase if `live`.
+fn publish(app: &AppHandle, event: LogEvent, live: bool) {
+    let state = app.state::<GameLogState>();
+    if !state.0.lock().unwrap().apply(&event) {
+        return;
+    }
+
+    match &event {
+        LogEvent::Pregame { match_id } if live => game::apply(app, Signal::Pregame(match_id.clone())),
+        LogEvent::CoreGame { match_id } if live => game::apply(app, Signal::CoreGame(match_id.clone())),
+        _ => {}
+    }
+    app.emit_all("game-log", event).unwrap();
+}
+
+#[tauri::command]
+pub fn game_log(state: State<'_, GameLogState>) -> GameLogInfo {
+    state.0.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION: &str = "[2024.04.09-18.02.15:531][  0]LogShooter: Display: CI server version: release-08.07-shipping-9-2444158";
+    const REGION: &str = "[2024.04.09-18.05.12:210][406]LogPlatformCommon: Platform HTTP Query End. QueryName: [Pregame_GetPlayer], URL [GET https://glz-eu-1.eu.a.pvp.net/pregame/v1/players/0d1c4f2e-7b35-4e6c-9a3e-1f8b2c4d5e6f], Response Code: [404]";
+    const PREGAME: &str = "[2024.04.09-18.06.01:044][945]LogPlatformCommon: Platform HTTP Query End. QueryName: [Pregame_GetMatch], URL [GET https://glz-eu-1.eu.a.pvp.net/pregame/v1/matches/3a7c5f2e-1b1d-4e8f-9c5a-6f2d1e0b8a7c], Response Code: [200]";
+    const CORE_GAME: &str = "[2024.04.09-18.07.30:802][512]LogPlatformCommon: Platform HTTP Query End. QueryName: [CoreGame_FetchMatch], URL [GET https://glz-eu-1.eu.a.pvp.net/core-game/v1/matches/3a7c5f2e-1b1d-4e8f-9c5a-6f2d1e0b8a7c/loadouts], Response Code: [200]";
+
+    #[test]
+    fn parse_reads_sample_lines() {
+        let parser = LogParser::new();
+        assert_eq!(parser.parse(VERSION), Some(LogEvent::Version {
+            version: "release-08.07-shipping-9-2444158".to_string()
+        }));
+        assert_eq!(parser.parse(REGION), Some(LogEvent::Region {
+            region: "eu".to_string(),
+            shard: "eu".to_string()
+        }));
+        assert_eq!(parser.parse(PREGAME), Some(LogEvent::Pregame {
+            match_id: "3a7c5f2e-1b1d-4e8f-9c5a-6f2d1e0b8a7c".to_string()
+        }));
+        assert_eq!(parser.parse(CORE_GAME), Some(LogEvent::CoreGame {
+            match_id: "3a7c5f2e-1b1d-4e8f-9c5a-6f2d1e0b8a7c".to_string()
+        }));
+        assert_eq!(parser.parse("[2024.04.09-18.02.15:531][  0]LogInit: Display: Running engine for game: ShooterGame"), None);
+    }
+
+    #[test]
+    fn apply_reports_only_changes() {
+        let parser = LogParser::new();
+        let mut info = GameLogInfo::default();
+        for line in [VERSION, REGION, PREGAME, CORE_GAME] {
+            assert!(info.apply(&parser.parse(line).unwrap()));
+            assert!(!info.apply(&parser.parse(line).unwrap()));
+        }
+
+        assert_eq!(info.region.as_deref(), Some("eu"));
+        assert_eq!(info.shard.as_deref(), Some("eu"));
+        assert_eq!(info.version.as_deref(), Some("release-08.07-shipping-9-2444158"));
+        assert_eq!(info.pregame_id.as_deref(), Some("3a7c5f2e-1b1d-4e8f-9c5a-6f2d1e0b8a7c"));
+        assert_eq!(info.match_id.as_deref(), Some("3a7c5f2e-1b1d-4e8f-9c5a-6f2d1e0b8a7c"));
+
+        // A different shard in the same region is still a change.
+        assert!(info.apply(&LogEvent::Region { region: "eu".to_string(), shard: "pbe".to_string() }));
+    }
+}
@@ -1,14 +1,20 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod game;
 mod http;
+mod lockfile;
+mod logfile;
+mod riot;
+mod websocket;
+
+#[cfg(test)]
+mod testing;
 
 use log::LevelFilter;
-use native_tls::TlsConnector;
 use serde::Serialize;
 use tauri::{AppHandle, Manager};
 use tauri_plugin_log::LogTarget;
-use tokio_tungstenite::Connector;
 
 #[derive(Clone, Serialize)]
 struct SingleInstance {
@@ -17,11 +23,9 @@ struct SingleInstance {
 }
 
 fn main() {
-    // Prepare a TLS connector for invalid certificates.
-    let tls_connector = TlsConnector::builder()
-        .danger_accept_invalid_certs(true)
-        .build()
-        .unwrap();
+    // Read the replay mode configuration.
+    let args: Vec<String> = std::env::args().collect();
+    let replay = http::ReplayConfig::from_args(&args);
 
     // Build the Tauri app.
     tauri::Builder::default()
@@ -29,12 +33,60 @@ fn main() {
             .level(LevelFilter::Info)
             .targets([LogTarget::Webview, LogTarget::Stdout, LogTarget::LogDir])
             .build())
-        .plugin(tauri_plugin_websocket::Builder::default()
-            .tls_connector(Connector::NativeTls(tls_connector))
-            .build())
         .plugin(tauri_plugin_positioner::init())
         .plugin(tauri_plugin_single_instance::init(single_instance))
-        .invoke_handler(tauri::generate_handler![http::fetch])
+        .manage(game::GameState::default())
+        .manage(http::HttpState::new(replay.clone()).expect("failed to create HTTP clients"))
+        .manage(lockfile::LockfileState::default())
+        .manage(logfile::GameLogState::default())
+        .manage(riot::presence::PresenceState::default())
+        .manage(riot::remote::RemoteState::default())
+        .manage(websocket::SocketState::default())
+        .setup(move |app| {
+            app.state::<http::HttpState>().attach(app.handle());
+            match replay {
+                // Replay mode does not need VALORANT to be running.
+                Some(replay) => {
+                    lockfile::pin(&app.handle(), replay.lockfile());
+                    logfile::seed(&app.handle(), replay.region, replay.shard);
+                }
+                None => {
+                    lockfile::watch(app.handle());
+                    logfile::tail(app.handle());
+                }
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            game::game_phase,
+            http::fetch,
+            http::fetch_many,
+            http::cancel_fetch,
+            http::http_queue,
+            http::http_cache,
+            http::http_cache_clear,
+            http::har_start,
+            http::har_stop,
+            http::proxy,
+            http::set_proxy,
+            lockfile::lockfile,
+            logfile::game_log,
+            riot::local::get_entitlements_token,
+            riot::local::get_chat_session,
+            riot::local::get_sessions,
+            riot::local::get_help,
+            riot::remote::remote_setup,
+            riot::remote::get_current_game,
+            riot::remote::get_game_data,
+            riot::remote::get_current_pregame,
+            riot::remote::get_pregame_data,
+            riot::remote::get_match_history,
+            riot::remote::get_match_data,
+            websocket::ws_connect,
+            websocket::ws_state,
+            websocket::ws_subscribe,
+            websocket::ws_unsubscribe
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::State;
+use crate::http::{HttpRequest, HttpState};
+use crate::lockfile::{LockfileInfo, LockfileState};
+use crate::riot::{self, RiotError};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitlementsTokenResponse {
+    /// Used as the token in requests.
+    pub access_token: String,
+    pub entitlements: Vec<Value>,
+    pub issuer: String,
+    /// Player UUID.
+    pub subject: String,
+    /// Used as the entitlement in requests.
+    pub token: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChatSessionResponse {
+    pub federated: bool,
+    pub game_name: String,
+    pub game_tag: String,
+    pub loaded: bool,
+    pub name: String,
+    pub pid: String,
+    /// Player UUID.
+    pub puuid: String,
+    pub region: String,
+    pub resource: String,
+    pub state: String
+}
+
+/// All active Riot Games sessions, keyed by session ID.
+pub type SessionsResponse = HashMap<String, ProductSession>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductSession {
+    pub exit_code: i32,
+    pub exit_reason: Option<String>,
+    pub is_internal: bool,
+    pub launch_configuration: LaunchConfiguration,
+    pub patchline_full_name: String,
+    pub patchline_id: String,
+    pub phase: String,
+    pub product_id: String,
+    pub version: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchConfiguration {
+    pub arguments: Vec<String>,
+    pub executable: String,
+    pub locale: Option<String>,
+    pub voice_locale: Option<String>,
+    pub working_directory: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LocalHelpResponse {
+    pub events: HashMap<String, String>,
+    pub functions: HashMap<String, String>,
+    pub types: HashMap<String, String>
+}
+
+/// A client for the local Riot Client API.
+pub struct LocalClient<'a> {
+    http: &'a HttpState,
+    base_url: String,
+    authorization: String
+}
+
+impl<'a> LocalClient<'a> {
+    pub fn new(http: &'a HttpState, info: &LockfileInfo) -> Self {
+        Self {
+            http,
+            base_url: format!("{}://127.0.0.1:{}", info.protocol, info.port),
+            authorization: format!("Basic {}",
+                BASE64_STANDARD.encode(format!("riot:{}", info.password)))
+        }
+    }
+
+    /// Creates a client for the running Riot Client.
+    pub fn connect(http: &'a HttpState, state: &LockfileState) -> Result<Self, RiotError> {
+        let info = state.current()?;
+        http.set_local_port(Some(info.port));
+
+        Ok(Self::new(http, &info))
+    }
+
+    /// Performs a GET request against the local API.
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, RiotError> {
+        riot::request(self.http, HttpRequest::new("GET", format!("{}/{}", self.base_url, path))
+            .header("Authorization", &self.authorization)).await
+    }
+
+    /// Fetches the entitlements token for the player.
+    pub async fn entitlements_token(&self) -> Result<EntitlementsTokenResponse, RiotError> {
+        self.get("entitlements/v1/token").await
+    }
+
+    /// Fetches the active chat session for the player.
+    pub async fn chat_session(&self) -> Result<ChatSessionResponse, RiotError> {
+        self.get("chat/v1/session").await
+    }
+
+    /// Fetches all active Riot Games sessions.
+    pub async fn sessions(&self) -> Result<SessionsResponse, RiotError> {
+        self.get("product-session/v1/sessions").await
+    }
+
+    /// Fetches the help information for the local API.
+    pub async fn help(&self) -> Result<LocalHelpResponse, RiotError> {
+        self.get("help").await
+    }
+}
+
+#[tauri::command]
+pub async fn get_entitlements_token(
+    http: State<'_, HttpState>, lockfile: State<'_, LockfileState>
+) -> Result<EntitlementsTokenResponse, RiotError> {
+    LocalClient::connect(&http, &lockfile)?.entitlements_token().await
+}
+
+#[tauri::command]
+pub async fn get_chat_session(
+    http: State<'_, HttpState>, lockfile: State<'_, LockfileState>
+) -> Result<ChatSessionResponse, RiotError> {
+    LocalClient::connect(&http, &lockfile)?.chat_session().await
+}
+
+#[tauri::command]
+pub async fn get_sessions(
+    http: State<'_, HttpState>, lockfile: State<'_, LockfileState>
+) -> Result<SessionsResponse, RiotError> {
+    LocalClient::connect(&http, &lockfile)?.sessions().await
+}
+
+#[tauri::command]
+pub async fn get_help(
+    http: State<'_, HttpState>, lockfile: State<'_, LockfileState>
+) -> Result<LocalHelpResponse, RiotError> {
+    LocalClient::connect(&http, &lockfile)?.help().await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+    use crate::testing::{self, TestServer};
+
+    /// Starts a local API with a self-signed certificate which answers every request the same way.
+    async fn serve(status: u16, body: Value) -> TestServer {
+        let body = body.to_string();
+        TestServer::start(Some(testing::tls_acceptor()), move |_| testing::response(
+            status, &[("Content-Type", "application/json")], &body)).await
+    }
+
+    fn http(server: &TestServer) -> HttpState {
+        let http = HttpState::new(None).unwrap();
+        http.set_local_port(Some(server.port));
+        http
+    }
+
+    /// Checks the request line and the Basic auth header of the only request received.
+    fn assert_request(server: &TestServer, path: &str) {
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with(&format!("GET {} HTTP/1.1\r\n", path)), "{}", requests[0]);
+        assert_eq!(testing::header(&requests[0], "Authorization").as_deref(), Some("Basic cmlvdDpzZWNyZXQ="));
+    }
+
+    #[tokio::test]
+    async fn entitlements_token() {
+        let server = serve(200, json!({
+            "accessToken": "access",
+            "entitlements": [],
+            "issuer": "https://entitlements.auth.riotgames.com",
+            "subject": "puuid",
+            "token": "entitlement"
+        })).await;
+        let http = http(&server);
+
+        let token = LocalClient::new(&http, &testing::lockfile(server.port)).entitlements_token().await.unwrap();
+        assert_eq!(token.access_token, "access");
+        assert_eq!(token.subject, "puuid");
+        assert_eq!(token.token, "entitlement");
+        assert_request(&server, "/entitlements/v1/token");
+    }
+
+    #[tokio::test]
+    async fn chat_session() {
+        let server = serve(200, json!({
+            "federated": true,
+            "game_name": "Player",
+            "game_tag": "NA1",
+            "loaded": true,
+            "name": "Player",
+            "pid": "player@na1.pvp.net",
+            "puuid": "puuid",
+            "region": "na1",
+            "resource": "RC-1",
+            "state": "connected"
+        })).await;
+        let http = http(&server);
+
+        let session = LocalClient::new(&http, &testing::lockfile(server.port)).chat_session().await.unwrap();
+        assert_eq!(session.game_name, "Player");
+        assert_eq!(session.game_tag, "NA1");
+        assert_eq!(session.puuid, "puuid");
+        assert_request(&server, "/chat/v1/session");
+    }
+
+    #[tokio::test]
+    async fn sessions() {
+        let server = serve(200, json!({
+            "session-id": {
+                "exitCode": 0,
+                "exitReason": null,
+                "isInternal": false,
+                "launchConfiguration": {
+                    "arguments": ["-ares-deployment=na"],
+                    "executable": "VALORANT.exe",
+                    "locale": "en_US",
+                    "voiceLocale": null,
+                    "workingDirectory": "C:/Riot Games/VALORANT/live"
+                },
+                "patchlineFullName": "VALORANT (live)",
+                "patchlineId": "live",
+                "phase": "Gameplay",
+                "productId": "valorant",
+                "version": "release-08.00"
+            }
+        })).await;
+        let http = http(&server);
+
+        let sessions = LocalClient::new(&http, &testing::lockfile(server.port)).sessions().await.unwrap();
+        let session = &sessions["session-id"];
+        assert_eq!(session.product_id, "valorant");
+        assert_eq!(session.launch_configuration.arguments, vec!["-ares-deployment=na"]);
+        assert_eq!(session.launch_configuration.voice_locale, None);
+        assert_request(&server, "/product-session/v1/sessions");
+    }
+
+    #[tokio::test]
+    async fn help() {
+        let server = serve(200, json!({
+            "events": { "OnJsonApiEvent": "All JSON API events" },
+            "functions": {},
+            "types": {}
+        })).await;
+        let http = http(&server);
+
+        let help = LocalClient::new(&http, &testing::lockfile(server.port)).help().await.unwrap();
+        assert_eq!(help.events["OnJsonApiEvent"], "All JSON API events");
+        assert!(help.functions.is_empty());
+        assert_request(&server, "/help");
+    }
+
+    #[tokio::test]
+    async fn bad_status() {
+        let server = serve(404, json!({ "errorCode": "RESOURCE_NOT_FOUND" })).await;
+        let http = http(&server);
+
+        match LocalClient::new(&http, &testing::lockfile(server.port)).chat_session().await {
+            Err(RiotError::Status { status, body }) => {
+                assert_eq!(status, 404);
+                assert_eq!(body, r#"{"errorCode":"RESOURCE_NOT_FOUND"}"#);
+            }
+            other => panic!("expected a status error, got {:?}", other.map(|_| ()))
+        }
+    }
+}
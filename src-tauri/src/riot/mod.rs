@@ -0,0 +1,64 @@
+pub mod local;
+pub mod presence;
+pub mod remote;
+
+use std::fmt;
+use log::error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use crate::http::{self, HttpError, HttpRequest, HttpState};
+use crate::lockfile::LockfileError;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum RiotError {
+    /// The Riot Client lockfile could not be used.
+    Lockfile(LockfileError),
+    /// The request could not be sent.
+    Http(HttpError),
+    /// The API returned a non-200 response.
+    Status { status: u16, body: String },
+    /// The response body did not match the expected type.
+    Decode(String),
+    /// Required client or game information is not available yet.
+    Unavailable(String)
+}
+
+impl fmt::Display for RiotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiotError::Lockfile(error) => write!(f, "{}", error),
+            RiotError::Http(error) => write!(f, "{}", error),
+            RiotError::Status { status, body } => write!(f, "Bad response: {} {}", status, body),
+            RiotError::Decode(error) => write!(f, "Unable to decode response: {}", error),
+            RiotError::Unavailable(reason) => write!(f, "{}", reason)
+        }
+    }
+}
+
+impl From<HttpError> for RiotError {
+    fn from(error: HttpError) -> Self {
+        RiotError::Http(error)
+    }
+}
+
+impl From<LockfileError> for RiotError {
+    fn from(error: LockfileError) -> Self {
+        RiotError::Lockfile(error)
+    }
+}
+
+/// Sends a request and decodes the JSON response.
+async fn request<T: DeserializeOwned>(http: &HttpState, request: HttpRequest) -> Result<T, RiotError> {
+    let response = http::send(http, request).await?;
+    let body = response.bytes();
+
+    if response.status != 200 {
+        let body = String::from_utf8_lossy(&body).to_string();
+        error!("Riot API did not return a good response: {} {}", response.status, body);
+        return Err(RiotError::Status { status: response.status, body });
+    }
+
+    serde_json::from_slice(&body)
+        .map_err(|error| RiotError::Decode(error.to_string()))
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use base64::prelude::*;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use crate::game::{self, Signal};
+use crate::riot::remote::RemoteState;
+use crate::websocket::{EventType, JsonApiEvent};
+
+/// The local API path presence events are published on.
+pub const PRESENCES_URI: &str = "/chat/v4/presences";
+
+// <editor-fold defaultstate="collapsed" desc="Chat Types">
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PresencesPayload {
+    pub presences: Vec<ChatPresence>
+}
+
+/// A player's presence, as sent by the chat API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChatPresence {
+    /// Player UUID.
+    pub puuid: String,
+    pub product: String,
+    /// Base64-encoded JSON with the game state; see `PrivatePresence`.
+    #[serde(default)]
+    pub private: Option<String>
+}
+
+/// The decoded `private` field of a VALORANT presence.
+/// Older clients send every field at the top level;
+/// newer ones group them into `*PresenceData` objects.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrivatePresence {
+    session_loop_state: Option<String>,
+    party_id: Option<String>,
+    party_size: Option<u32>,
+    queue_id: Option<String>,
+    match_map: Option<String>,
+    party_owner_match_score_ally_team: Option<i32>,
+    party_owner_match_score_enemy_team: Option<i32>,
+    competitive_tier: Option<u32>,
+    match_presence_data: Option<MatchPresenceData>,
+    party_presence_data: Option<PartyPresenceData>,
+    player_presence_data: Option<PlayerPresenceData>
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MatchPresenceData {
+    session_loop_state: Option<String>,
+    queue_id: Option<String>,
+    match_map: Option<String>
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartyPresenceData {
+    party_id: Option<String>,
+    party_size: Option<u32>,
+    party_owner_match_score_ally_team: Option<i32>,
+    party_owner_match_score_enemy_team: Option<i32>
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlayerPresenceData {
+    competitive_tier: Option<u32>
+}
+
+// </editor-fold>
+
+// <editor-fold defaultstate="collapsed" desc="Presence Types">
+
+/// The score of the party owner's current match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Score {
+    pub ally: i32,
+    pub enemy: i32
+}
+
+/// A player's VALORANT presence.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    /// Player UUID.
+    pub puuid: String,
+    /// `MENUS`, `PREGAME` or `INGAME`.
+    pub session_loop_state: Option<String>,
+    pub party_id: Option<String>,
+    pub party_size: Option<u32>,
+    pub queue_id: Option<String>,
+    /// The map asset path, e.g. `/Game/Maps/Ascent/Ascent`.
+    pub match_map: Option<String>,
+    pub score: Option<Score>,
+    pub competitive_tier: Option<u32>
+}
+
+/// Emitted as `presence-changed` when a player's presence changes.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceChanged {
+    pub puuid: String,
+    /// The names of the fields which changed,
+    /// or `online` if the player appeared or went offline.
+    pub changed: Vec<&'static str>,
+    /// None if the player was not seen before.
+    pub previous: Option<Presence>,
+    /// None if the player went offline.
+    pub current: Option<Presence>
+}
+
+// </editor-fold>
+
+impl Presence {
+    /// Decodes a chat presence.
+    /// Returns none for other products and unreadable payloads.
+    pub fn decode(presence: &ChatPresence) -> Option<Self> {
+        if presence.product != "valorant" {
+            return None;
+        }
+
+        let bytes = BASE64_STANDARD.decode(presence.private.as_deref()?).ok()?;
+        let private: PrivatePresence = match serde_json::from_slice(&bytes) {
+            Ok(private) => private,
+            Err(error) => {
+                warn!("Unable to decode the presence of {}: {}", presence.puuid, error);
+                return None;
+            }
+        };
+
+        let game = private.match_presence_data.unwrap_or_default();
+        let party = private.party_presence_data.unwrap_or_default();
+        let player = private.player_presence_data.unwrap_or_default();
+
+        let ally = private.party_owner_match_score_ally_team.or(party.party_owner_match_score_ally_team);
+        let enemy = private.party_owner_match_score_enemy_team.or(party.party_owner_match_score_enemy_team);
+
+        Some(Self {
+            puuid: presence.puuid.clone(),
+            session_loop_state: private.session_loop_state.or(game.session_loop_state),
+            party_id: private.party_id.or(party.party_id),
+            party_size: private.party_size.or(party.party_size),
+            queue_id: private.queue_id.or(game.queue_id),
+            match_map: private.match_map.or(game.match_map),
+            score: ally.zip(enemy).map(|(ally, enemy)| Score { ally, enemy }),
+            competitive_tier: private.competitive_tier.or(player.competitive_tier)
+        })
+    }
+
+    /// Returns the names of the fields which differ from another presence.
+    pub fn diff(&self, other: &Presence) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.session_loop_state != other.session_loop_state {
+            changed.push("sessionLoopState");
+        }
+        if self.party_id != other.party_id {
+            changed.push("partyId");
+        }
+        if self.party_size != other.party_size {
+            changed.push("partySize");
+        }
+        if self.queue_id != other.queue_id {
+            changed.push("queueId");
+        }
+        if self.match_map != other.match_map {
+            changed.push("matchMap");
+        }
+        if self.score != other.score {
+            changed.push("score");
+        }
+        if self.competitive_tier != other.competitive_tier {
+            changed.push("competitiveTier");
+        }
+
+        changed
+    }
+}
+
+/// The last known presence of each player, keyed by player UUID.
+#[derive(Default)]
+pub struct PresenceState(pub Mutex<HashMap<String, Presence>>);
+
+/// Applies the presences of an event to the last known presences.
+/// Returns a change for every player whose presence changed.
+fn apply(
+    presences: &mut HashMap<String, Presence>, event_type: EventType, payload: &PresencesPayload
+) -> Vec<PresenceChanged> {
+    let mut changes = Vec::new();
+    for presence in &payload.presences {
+        let current = match event_type {
+            EventType::Delete => None,
+            _ => match Presence::decode(presence) {
+                Some(current) => Some(current),
+                None => continue
+            }
+        };
+
+        let previous = match &current {
+            Some(current) => presences.insert(presence.puuid.clone(), current.clone()),
+            None => presences.remove(&presence.puuid)
+        };
+
+        let changed = match (&previous, &current) {
+            (Some(previous), Some(current)) => previous.diff(current),
+            (None, None) => continue,
+            _ => vec!["online"]
+        };
+        if !changed.is_empty() {
+            changes.push(PresenceChanged {
+                puuid: presence.puuid.clone(),
+                changed,
+                previous,
+                current
+            });
+        }
+    }
+
+    changes
+}
+
+/// Applies a presence event, emitting `presence-changed` for every player whose presence changed.
+pub fn handle(app: &AppHandle, event: &JsonApiEvent) {
+    let payload: PresencesPayload = match serde_json::from_value(event.data.clone()) {
+        Ok(payload) => payload,
+        Err(error) => {
+            warn!("Received a malformed presence event: {}", error);
+            return;
+        }
+    };
+
+    let state = app.state::<PresenceState>();
+    let changes = apply(&mut state.0.lock().unwrap(), event.event_type, &payload);
+
+    // The local player's session loop state drives the game phase.
+    let local_puuid = app.state::<RemoteState>().session().ok().map(|session| session.puuid);
+    for change in changes {
+        let session_loop_state = change.current.as_ref()
+            .and_then(|current| current.session_loop_state.clone());
+        let local = Some(&change.puuid) == local_puuid.as_ref();
+
+        if let Err(error) = app.emit_all("presence-changed", change) {
+            warn!("Unable to emit 'presence-changed': {}", error);
+        }
+        if local {
+            if let Some(state) = session_loop_state {
+                game::apply(app, Signal::SessionLoopState(state));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use super::*;
+
+    fn chat_presence(puuid: &str, product: &str, private: Value) -> ChatPresence {
+        ChatPresence {
+            puuid: puuid.to_string(),
+            product: product.to_string(),
+            private: Some(BASE64_STANDARD.encode(private.to_string()))
+        }
+    }
+
+    fn menus(puuid: &str, party_size: u32) -> ChatPresence {
+        chat_presence(puuid, "valorant", json!({
+            "sessionLoopState": "MENUS",
+            "partyId": "party",
+            "partySize": party_size
+        }))
+    }
+
+    fn payload(presences: Vec<ChatPresence>) -> PresencesPayload {
+        PresencesPayload { presences }
+    }
+
+    #[test]
+    fn decode_reads_the_legacy_layout() {
+        let presence = Presence::decode(&chat_presence("puuid", "valorant", json!({
+            "sessionLoopState": "INGAME",
+            "partyId": "party",
+            "partySize": 2,
+            "queueId": "competitive",
+            "matchMap": "/Game/Maps/Ascent/Ascent",
+            "partyOwnerMatchScoreAllyTeam": 7,
+            "partyOwnerMatchScoreEnemyTeam": 5,
+            "competitiveTier": 12
+        }))).unwrap();
+
+        assert_eq!(presence, Presence {
+            puuid: "puuid".to_string(),
+            session_loop_state: Some("INGAME".to_string()),
+            party_id: Some("party".to_string()),
+            party_size: Some(2),
+            queue_id: Some("competitive".to_string()),
+            match_map: Some("/Game/Maps/Ascent/Ascent".to_string()),
+            score: Some(Score { ally: 7, enemy: 5 }),
+            competitive_tier: Some(12)
+        });
+    }
+
+    #[test]
+    fn decode_reads_the_nested_layout() {
+        let presence = Presence::decode(&chat_presence("puuid", "valorant", json!({
+            "matchPresenceData": {
+                "sessionLoopState": "PREGAME",
+                "queueId": "unrated",
+                "matchMap": "/Game/Maps/Bonsai/Bonsai"
+            },
+            "partyPresenceData": {
+                "partyId": "party",
+                "partySize": 5,
+                "partyOwnerMatchScoreAllyTeam": 0,
+                "partyOwnerMatchScoreEnemyTeam": 0
+            },
+            "playerPresenceData": {
+                "competitiveTier": 20
+            }
+        }))).unwrap();
+
+        assert_eq!(presence.session_loop_state.as_deref(), Some("PREGAME"));
+        assert_eq!(presence.party_id.as_deref(), Some("party"));
+        assert_eq!(presence.party_size, Some(5));
+        assert_eq!(presence.queue_id.as_deref(), Some("unrated"));
+        assert_eq!(presence.match_map.as_deref(), Some("/Game/Maps/Bonsai/Bonsai"));
+        assert_eq!(presence.score, Some(Score { ally: 0, enemy: 0 }));
+        assert_eq!(presence.competitive_tier, Some(20));
+    }
+
+    #[test]
+    fn decode_needs_both_scores() {
+        let presence = Presence::decode(&chat_presence("puuid", "valorant", json!({
+            "partyOwnerMatchScoreAllyTeam": 3
+        }))).unwrap();
+        assert_eq!(presence.score, None);
+    }
+
+    #[test]
+    fn decode_ignores_other_products_and_bad_payloads() {
+        assert_eq!(Presence::decode(&chat_presence("puuid", "league_of_legends", json!({
+            "sessionLoopState": "MENUS"
+        }))), None);
+
+        let mut presence = menus("puuid", 1);
+        presence.private = None;
+        assert_eq!(Presence::decode(&presence), None);
+        presence.private = Some("not base64!".to_string());
+        assert_eq!(Presence::decode(&presence), None);
+        presence.private = Some(BASE64_STANDARD.encode("not json"));
+        assert_eq!(Presence::decode(&presence), None);
+        presence.private = Some(BASE64_STANDARD.encode(r#"{"partySize":"two"}"#));
+        assert_eq!(Presence::decode(&presence), None);
+    }
+
+    #[test]
+    fn diff_lists_changed_fields() {
+        let previous = Presence::decode(&menus("puuid", 1)).unwrap();
+        assert!(previous.diff(&previous).is_empty());
+
+        let mut current = previous.clone();
+        current.session_loop_state = Some("PREGAME".to_string());
+        current.party_size = Some(2);
+        current.score = Some(Score { ally: 1, enemy: 0 });
+        current.competitive_tier = Some(3);
+        assert_eq!(previous.diff(&current), vec!["sessionLoopState", "partySize", "score", "competitiveTier"]);
+
+        let mut current = previous.clone();
+        current.party_id = None;
+        current.queue_id = Some("swiftplay".to_string());
+        current.match_map = Some("/Game/Maps/Ascent/Ascent".to_string());
+        assert_eq!(previous.diff(&current), vec!["partyId", "queueId", "matchMap"]);
+    }
+
+    #[test]
+    fn apply_reports_players_coming_online_and_changing() {
+        let mut presences = HashMap::new();
+
+        let changes = apply(&mut presences, EventType::Create, &payload(vec![
+            menus("a", 1),
+            chat_presence("b", "league_of_legends", json!({})),
+        ]));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].puuid, "a");
+        assert_eq!(changes[0].changed, vec!["online"]);
+        assert_eq!(changes[0].previous, None);
+        assert_eq!(changes[0].current, presences.get("a").cloned());
+        assert_eq!(presences.len(), 1);
+
+        // An unchanged presence is not reported.
+        let changes = apply(&mut presences, EventType::Update, &payload(vec![menus("a", 1)]));
+        assert!(changes.is_empty());
+
+        let changes = apply(&mut presences, EventType::Update, &payload(vec![menus("a", 2)]));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].changed, vec!["partySize"]);
+        assert_eq!(changes[0].previous.as_ref().unwrap().party_size, Some(1));
+        assert_eq!(changes[0].current.as_ref().unwrap().party_size, Some(2));
+    }
+
+    #[test]
+    fn apply_reports_players_going_offline() {
+        let mut presences = HashMap::new();
+        apply(&mut presences, EventType::Create, &payload(vec![menus("a", 1)]));
+
+        // An undecodable presence leaves the last known one in place.
+        let mut unreadable = menus("a", 2);
+        unreadable.private = Some("not base64!".to_string());
+        assert!(apply(&mut presences, EventType::Update, &payload(vec![unreadable])).is_empty());
+        assert_eq!(presences["a"].party_size, Some(1));
+
+        let changes = apply(&mut presences, EventType::Delete, &payload(vec![menus("a", 1), menus("b", 1)]));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].puuid, "a");
+        assert_eq!(changes[0].changed, vec!["online"]);
+        assert_eq!(changes[0].previous.as_ref().unwrap().party_size, Some(1));
+        assert_eq!(changes[0].current, None);
+        assert!(presences.is_empty());
+    }
+}
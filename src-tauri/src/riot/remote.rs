@@ -0,0 +1,959 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::State;
+use crate::http::{HttpRequest, HttpState};
+use crate::lockfile::LockfileState;
+use crate::logfile::GameLogState;
+use crate::riot::{self, RiotError};
+use crate::riot::local::LocalClient;
+
+/// Base64-encoded platform information sent as `X-Riot-ClientPlatform`.
+pub const CLIENT_PLATFORM: &str = "ew0KCSJwbGF0Zm9ybVR5cGUiOiAiUEMiLA0KCSJwbGF0Zm9ybU9TIjogIldpbmRvd3MiLA0KCSJwbGF0Zm9ybU9TVmVyc2lvbiI6ICIxMC4wLjE5MDQyLjEuMjU2LjY0Yml0IiwNCgkicGxhdGZvcm1DaGlwc2V0IjogIlVua25vd24iDQp9";
+
+// <editor-fold defaultstate="collapsed" desc="Shared Types">
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlayerIdentity {
+    /// Player UUID.
+    pub subject: String,
+    #[serde(rename = "PlayerCardID")]
+    pub player_card_id: String,
+    #[serde(rename = "PlayerTitleID")]
+    pub player_title_id: String,
+    pub account_level: i32,
+    #[serde(rename = "PreferredLevelBorderID")]
+    pub preferred_level_border_id: String,
+    pub incognito: bool,
+    pub hide_account_level: bool
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SeasonalBadgeInfo {
+    #[serde(rename = "SeasonID")]
+    pub season_id: String,
+    pub number_of_wins: i32,
+    pub wins_by_tier: Option<Value>,
+    pub rank: i32,
+    pub leaderboard_rank: i32
+}
+
+// </editor-fold>
+
+// <editor-fold defaultstate="collapsed" desc="Pre-Game Types">
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreGamePlayerResponse {
+    /// Player UUID.
+    pub subject: String,
+    /// Pre-game match ID.
+    #[serde(rename = "MatchID")]
+    pub match_id: String,
+    pub version: i64
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreGameTeam {
+    #[serde(rename = "TeamID")]
+    pub team_id: String,
+    pub players: Vec<PreGamePlayer>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreGamePlayer {
+    /// Player UUID.
+    pub subject: String,
+    #[serde(rename = "CharacterID")]
+    pub character_id: String,
+    pub character_selection_state: String,
+    pub pregame_player_state: String,
+    pub competitive_tier: i32,
+    pub player_identity: PlayerIdentity,
+    pub seasonal_badge_info: SeasonalBadgeInfo,
+    pub is_captain: bool
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreGameMatchResponse {
+    /// Pre-game match ID.
+    #[serde(rename = "ID")]
+    pub id: String,
+    pub version: i64,
+    pub teams: Vec<PreGameTeam>,
+    pub ally_team: Option<PreGameTeam>,
+    pub enemy_team: Option<PreGameTeam>,
+    pub observer_subjects: Vec<Value>,
+    pub match_coaches: Vec<Value>,
+    pub enemy_team_size: i32,
+    pub enemy_team_lock_count: i32,
+    pub pregame_state: String,
+    /// Date in ISO 8601 format.
+    pub last_updated: String,
+    #[serde(rename = "MapID")]
+    pub map_id: String,
+    pub map_select_pool: Vec<Value>,
+    #[serde(rename = "BannedMapIDs")]
+    pub banned_map_ids: Vec<Value>,
+    pub casted_votes: Option<Value>,
+    pub map_select_steps: Vec<Value>,
+    pub map_select_step: i32,
+    pub team1: String,
+    #[serde(rename = "GamePodID")]
+    pub game_pod_id: String,
+    pub mode: String,
+    #[serde(rename = "VoiceSessionID")]
+    pub voice_session_id: String,
+    #[serde(rename = "MUCName")]
+    pub muc_name: String,
+    /// JWT containing the match ID and player IDs.
+    pub team_match_token: String,
+    #[serde(rename = "QueueID")]
+    pub queue_id: String,
+    #[serde(rename = "ProvisioningFlowID")]
+    pub provisioning_flow_id: String,
+    pub is_ranked: bool,
+    #[serde(rename = "PhaseTimeRemainingNS")]
+    pub phase_time_remaining_ns: i64,
+    #[serde(rename = "StepTimeRemainingNS")]
+    pub step_time_remaining_ns: i64,
+    #[serde(rename = "altModesFlagADA")]
+    pub alt_modes_flag_ada: bool,
+    pub tournament_metadata: Option<Value>,
+    pub roster_metadata: Option<Value>
+}
+
+// </editor-fold>
+
+// <editor-fold defaultstate="collapsed" desc="Core-Game Types">
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CurrentGamePlayerResponse {
+    /// Player UUID.
+    pub subject: String,
+    /// Current game match ID.
+    #[serde(rename = "MatchID")]
+    pub match_id: String,
+    pub version: i64
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CurrentGameMatchResponse {
+    #[serde(rename = "MatchID")]
+    pub match_id: String,
+    pub version: i64,
+    pub state: String,
+    #[serde(rename = "MapID")]
+    pub map_id: String,
+    #[serde(rename = "ModeID")]
+    pub mode_id: String,
+    pub provisioning_flow: String,
+    #[serde(rename = "GamePodID")]
+    pub game_pod_id: String,
+    /// Chat room ID for "all" chat.
+    #[serde(rename = "AllMUCName")]
+    pub all_muc_name: String,
+    /// Chat room ID for "team" chat.
+    #[serde(rename = "TeamMUCName")]
+    pub team_muc_name: String,
+    #[serde(rename = "TeamVoiceID")]
+    pub team_voice_id: String,
+    /// JWT containing the match ID, participant IDs, and match region.
+    pub team_match_token: String,
+    pub is_reconnectable: bool,
+    pub connection_details: ConnectionDetails,
+    pub post_game_details: Option<Value>,
+    pub players: Vec<CurrentGamePlayer>,
+    pub matchmaking_data: Option<Value>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConnectionDetails {
+    pub game_server_hosts: Vec<String>,
+    pub game_server_host: String,
+    pub game_server_port: u32,
+    #[serde(rename = "GameServerObfuscatedIP")]
+    pub game_server_obfuscated_ip: i64,
+    pub game_client_hash: i64,
+    pub player_key: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CurrentGamePlayer {
+    /// Player UUID.
+    pub subject: String,
+    #[serde(rename = "TeamID")]
+    pub team_id: String,
+    #[serde(rename = "CharacterID")]
+    pub character_id: String,
+    pub player_identity: PlayerIdentity,
+    pub seasonal_badge_info: SeasonalBadgeInfo,
+    pub is_coach: bool,
+    pub is_associated: bool
+}
+
+// </editor-fold>
+
+// <editor-fold defaultstate="collapsed" desc="Match Types">
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MatchHistoryResponse {
+    /// Player UUID.
+    pub subject: String,
+    pub begin_index: i32,
+    pub end_index: i32,
+    pub total: i32,
+    pub history: Vec<MatchHistoryEntry>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MatchHistoryEntry {
+    #[serde(rename = "MatchID")]
+    pub match_id: String,
+    /// Milliseconds since epoch.
+    pub game_start_time: i64,
+    #[serde(rename = "QueueID")]
+    pub queue_id: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchDetailsResponse {
+    pub match_info: MatchInfo,
+    pub players: Vec<MatchPlayer>,
+    pub bots: Vec<Value>,
+    pub coaches: Vec<MatchCoach>,
+    pub teams: Option<Vec<MatchTeam>>,
+    pub round_results: Option<Vec<RoundResult>>,
+    pub kills: Option<Vec<Kill>>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchInfo {
+    pub match_id: String,
+    pub map_id: String,
+    pub game_pod_id: String,
+    pub game_loop_zone: String,
+    pub game_server_address: String,
+    pub game_version: String,
+    pub game_length_millis: Option<i64>,
+    pub game_start_millis: i64,
+    #[serde(rename = "provisioningFlowID")]
+    pub provisioning_flow_id: String,
+    pub is_completed: bool,
+    pub custom_game_name: String,
+    pub force_post_processing: bool,
+    #[serde(rename = "queueID")]
+    pub queue_id: String,
+    pub game_mode: String,
+    pub is_ranked: bool,
+    pub is_match_sampled: bool,
+    pub season_id: String,
+    pub completion_state: String,
+    pub platform_type: String,
+    pub premier_match_info: Value,
+    #[serde(rename = "partyRRPenalties")]
+    pub party_rr_penalties: Option<HashMap<String, f64>>,
+    pub should_match_disable_penalties: bool
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchPlayer {
+    /// Player UUID.
+    pub subject: String,
+    pub game_name: String,
+    pub tag_line: String,
+    pub platform_info: PlatformInfo,
+    pub team_id: String,
+    pub party_id: String,
+    pub character_id: String,
+    pub stats: Option<PlayerStats>,
+    pub round_damage: Option<Vec<RoundDamage>>,
+    pub competitive_tier: i32,
+    pub is_observer: bool,
+    pub player_card: String,
+    pub player_title: String,
+    pub preferred_level_border: Option<String>,
+    pub account_level: i32,
+    pub session_playtime_minutes: Option<i64>,
+    pub xp_modifications: Option<Vec<XpModification>>,
+    pub behavior_factors: Option<BehaviorFactors>,
+    pub new_player_experience_details: Option<Value>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformInfo {
+    pub platform_type: String,
+    #[serde(rename = "platformOS")]
+    pub platform_os: String,
+    #[serde(rename = "platformOSVersion")]
+    pub platform_os_version: String,
+    pub platform_chipset: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStats {
+    pub score: i32,
+    pub rounds_played: i32,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub playtime_millis: i64,
+    pub ability_casts: Option<AbilityCasts>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbilityCasts {
+    pub grenade_casts: i32,
+    pub ability1_casts: i32,
+    pub ability2_casts: i32,
+    pub ultimate_casts: i32
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoundDamage {
+    pub round: i32,
+    /// Player UUID.
+    pub receiver: String,
+    pub damage: i32
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct XpModification {
+    /// XP multiplier.
+    #[serde(rename = "Value")]
+    pub value: f64,
+    #[serde(rename = "ID")]
+    pub id: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BehaviorFactors {
+    pub afk_rounds: f64,
+    pub collisions: Option<f64>,
+    pub comms_rating_recovery: f64,
+    pub damage_participation_outgoing: f64,
+    pub friendly_fire_incoming: Option<f64>,
+    pub friendly_fire_outgoing: Option<f64>,
+    pub mouse_movement: Option<f64>,
+    pub stayed_in_spawn_rounds: Option<f64>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchCoach {
+    /// Player UUID.
+    pub subject: String,
+    pub team_id: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchTeam {
+    pub team_id: String,
+    pub won: bool,
+    pub rounds_played: i32,
+    pub rounds_won: i32,
+    pub num_points: i32
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundResult {
+    pub round_num: i32,
+    pub round_result: String,
+    pub round_ceremony: String,
+    pub winning_team: String,
+    /// Player UUID.
+    pub bomb_planter: Option<String>,
+    pub bomb_defuser: Option<String>,
+    /// Milliseconds since the start of the round; 0 if not planted.
+    pub plant_round_time: Option<i64>,
+    pub plant_player_locations: Option<Vec<PlayerLocation>>,
+    pub plant_location: Location,
+    pub plant_site: String,
+    /// Milliseconds since the start of the round; 0 if not defused.
+    pub defuse_round_time: Option<i64>,
+    pub defuse_player_locations: Option<Vec<PlayerLocation>>,
+    pub defuse_location: Location,
+    pub player_stats: Vec<RoundPlayerStats>,
+    /// Empty if the timer expired.
+    pub round_result_code: String,
+    pub player_economies: Option<Vec<Economy>>,
+    pub player_scores: Option<Vec<PlayerScore>>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Location {
+    pub x: f64,
+    pub y: f64
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerLocation {
+    /// Player UUID.
+    pub subject: String,
+    pub view_radians: f64,
+    pub location: Location
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundPlayerStats {
+    /// Player UUID.
+    pub subject: String,
+    pub kills: Vec<Kill>,
+    pub damage: Vec<Damage>,
+    pub score: i32,
+    pub economy: Economy,
+    pub ability: Value,
+    pub was_afk: bool,
+    pub was_penalized: bool,
+    pub stayed_in_spawn: bool
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Kill {
+    /// Milliseconds since the start of the game.
+    pub game_time: i64,
+    /// Milliseconds since the start of the round.
+    pub round_time: i64,
+    /// Player UUID.
+    pub killer: String,
+    /// Player UUID.
+    pub victim: String,
+    pub victim_location: Location,
+    pub assistants: Vec<String>,
+    pub player_locations: Vec<PlayerLocation>,
+    pub finishing_damage: FinishingDamage,
+    /// Only present in the match-wide kill list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round: Option<i32>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishingDamage {
+    pub damage_type: String,
+    /// Empty if the player was killed by the spike, fall damage, or melee.
+    pub damage_item: String,
+    pub is_secondary_fire_mode: bool
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Damage {
+    /// Player UUID.
+    pub receiver: String,
+    pub damage: i32,
+    pub legshots: i32,
+    pub bodyshots: i32,
+    pub headshots: i32
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Economy {
+    /// Player UUID; only present in per-round economy lists.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub loadout_value: i32,
+    /// Item ID.
+    pub weapon: String,
+    /// Armor ID.
+    pub armor: String,
+    pub remaining: i32,
+    pub spent: i32
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerScore {
+    /// Player UUID.
+    pub subject: String,
+    pub score: i32
+}
+
+// </editor-fold>
+
+/// Which remote API a request is sent to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Host {
+    /// `pd.<shard>.a.pvp.net`
+    Pd,
+    /// `glz-<region>-1.<shard>.a.pvp.net`
+    Glz
+}
+
+/// Credentials and routing information for the remote API.
+#[derive(Clone, Debug)]
+pub struct RemoteSession {
+    pub region: String,
+    pub shard: String,
+    pub client_version: String,
+    pub access_token: String,
+    pub entitlement_token: String,
+    /// Player UUID.
+    pub puuid: String,
+    /// Replaces the base URL of every remote API, e.g. with a local test server.
+    pub base_url: Option<String>
+}
+
+#[derive(Default)]
+pub struct RemoteState(pub Mutex<Option<RemoteSession>>);
+
+impl RemoteSession {
+    /// Creates a session using the local client's credentials.
+    pub async fn create(local: &LocalClient<'_>, region: String, shard: String) -> Result<Self, RiotError> {
+        let entitlements = local.entitlements_token().await?;
+        let sessions = local.sessions().await?;
+
+        // Find the VALORANT client.
+        let valorant = sessions.values().find(|session| session.product_id == "valorant");
+        let client_version = match valorant {
+            Some(session) => session.version.clone(),
+            None => return Err(RiotError::Unavailable("VALORANT session not found.".to_string()))
+        };
+
+        Ok(Self {
+            region,
+            shard,
+            client_version,
+            access_token: entitlements.access_token,
+            entitlement_token: entitlements.token,
+            puuid: entitlements.subject,
+            base_url: None
+        })
+    }
+
+    /// Returns the base URL of the given remote API.
+    pub fn base_url(&self, host: Host) -> String {
+        if let Some(base_url) = &self.base_url {
+            return base_url.clone();
+        }
+
+        match host {
+            Host::Pd => format!("https://pd.{}.a.pvp.net", self.shard),
+            Host::Glz => format!("https://glz-{}-1.{}.a.pvp.net", self.region, self.shard)
+        }
+    }
+
+    /// Performs a GET request against a remote API.
+    async fn get<T: DeserializeOwned>(&self, http: &HttpState, host: Host, path: &str) -> Result<T, RiotError> {
+        riot::request(http, HttpRequest::new("GET", format!("{}/{}", self.base_url(host), path))
+            .header("X-Riot-ClientPlatform", CLIENT_PLATFORM)
+            .header("X-Riot-ClientVersion", &self.client_version)
+            .header("X-Riot-Entitlements-JWT", &self.entitlement_token)
+            .header("Authorization", &format!("Bearer {}", self.access_token))).await
+    }
+}
+
+impl RemoteState {
+    /// Returns a copy of the current session.
+    pub fn session(&self) -> Result<RemoteSession, RiotError> {
+        match self.0.lock().unwrap().clone() {
+            Some(session) => Ok(session),
+            None => Err(RiotError::Unavailable("Remote session has not been set up.".to_string()))
+        }
+    }
+
+    /// Fetches new tokens from the local client and stores them in the session.
+    pub async fn refresh(&self, http: &HttpState, lockfile: &LockfileState) -> Result<RemoteSession, RiotError> {
+        let entitlements = LocalClient::connect(http, lockfile)?.entitlements_token().await?;
+
+        let mut current = self.0.lock().unwrap();
+        let session = match current.as_mut() {
+            Some(session) => session,
+            None => return Err(RiotError::Unavailable("Remote session has not been set up.".to_string()))
+        };
+        session.access_token = entitlements.access_token;
+        session.entitlement_token = entitlements.token;
+
+        Ok(session.clone())
+    }
+}
+
+/// A client for the remote API which shares the app's remote session.
+/// Expired tokens are refreshed from the local client.
+pub struct RemoteClient<'a> {
+    http: &'a HttpState,
+    remote: &'a RemoteState,
+    lockfile: &'a LockfileState
+}
+
+impl<'a> RemoteClient<'a> {
+    pub fn new(http: &'a HttpState, remote: &'a RemoteState, lockfile: &'a LockfileState) -> Self {
+        Self { http, remote, lockfile }
+    }
+
+    /// Returns the UUID of the logged in player.
+    pub fn puuid(&self) -> Result<String, RiotError> {
+        Ok(self.remote.session()?.puuid)
+    }
+
+    /// Performs a GET request against a remote API.
+    /// If the tokens were rejected, they are refreshed and the request is retried once.
+    async fn get<T: DeserializeOwned>(&self, host: Host, path: &str) -> Result<T, RiotError> {
+        let session = self.remote.session()?;
+        match session.get(self.http, host, path).await {
+            Err(RiotError::Status { status: 401 | 403, .. }) => {}
+            result => return result
+        }
+
+        info!("Remote API rejected the session tokens; refreshing entitlements.");
+        let session = self.remote.refresh(self.http, self.lockfile).await?;
+        session.get(self.http, host, path).await
+    }
+
+    /// Fetches the ID of the game the player is in.
+    pub async fn current_game(&self, uuid: &str) -> Result<CurrentGamePlayerResponse, RiotError> {
+        self.get(Host::Glz, &format!("core-game/v1/players/{}", uuid)).await
+    }
+
+    /// Fetches the data of the game with the specified ID.
+    pub async fn game_data(&self, game_id: &str) -> Result<CurrentGameMatchResponse, RiotError> {
+        self.get(Host::Glz, &format!("core-game/v1/matches/{}", game_id)).await
+    }
+
+    /// Fetches the ID of the pre-game the player is in.
+    pub async fn current_pregame(&self, uuid: &str) -> Result<PreGamePlayerResponse, RiotError> {
+        self.get(Host::Glz, &format!("pregame/v1/players/{}", uuid)).await
+    }
+
+    /// Fetches the data of the pre-game with the specified ID.
+    pub async fn pregame_data(&self, pregame_id: &str) -> Result<PreGameMatchResponse, RiotError> {
+        self.get(Host::Glz, &format!("pregame/v1/matches/{}", pregame_id)).await
+    }
+
+    /// Fetches the match history of the player.
+    pub async fn match_history(
+        &self, uuid: &str,
+        start_index: u32, end_index: u32, queue: &str
+    ) -> Result<MatchHistoryResponse, RiotError> {
+        self.get(Host::Pd, &format!(
+            "match-history/v1/history/{}?startIndex={}&endIndex={}&queue={}",
+            uuid, start_index, end_index, queue)).await
+    }
+
+    /// Fetches the data of the match with the specified ID.
+    pub async fn match_data(&self, match_id: &str) -> Result<MatchDetailsResponse, RiotError> {
+        self.get(Host::Pd, &format!("match-details/v1/matches/{}", match_id)).await
+    }
+}
+
+/// Sets up the remote session.
+/// The region and shard default to those found in `ShooterGame.log`.
+#[tauri::command]
+pub async fn remote_setup(
+    http: State<'_, HttpState>,
+    lockfile: State<'_, LockfileState>,
+    game_log: State<'_, GameLogState>,
+    remote: State<'_, RemoteState>,
+    region: Option<String>, shard: Option<String>
+) -> Result<(), RiotError> {
+    let log_info = game_log.0.lock().unwrap().clone();
+    let region = region.or(log_info.region);
+    let shard = shard.or(log_info.shard);
+    if region.is_none() || shard.is_none() {
+        return Err(RiotError::Unavailable("Failed to find the region and shard.".to_string()));
+    }
+
+    let local = LocalClient::connect(&http, &lockfile)?;
+    let session = RemoteSession::create(&local, region.unwrap(), shard.unwrap()).await?;
+    *remote.0.lock().unwrap() = Some(session);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_current_game(
+    http: State<'_, HttpState>,
+    remote: State<'_, RemoteState>, lockfile: State<'_, LockfileState>,
+    uuid: Option<String>
+) -> Result<CurrentGamePlayerResponse, RiotError> {
+    let client = RemoteClient::new(&http, &remote, &lockfile);
+    let uuid = match uuid {
+        Some(uuid) => uuid,
+        None => client.puuid()?
+    };
+    client.current_game(&uuid).await
+}
+
+#[tauri::command]
+pub async fn get_game_data(
+    http: State<'_, HttpState>,
+    remote: State<'_, RemoteState>, lockfile: State<'_, LockfileState>,
+    game_id: String
+) -> Result<CurrentGameMatchResponse, RiotError> {
+    RemoteClient::new(&http, &remote, &lockfile).game_data(&game_id).await
+}
+
+#[tauri::command]
+pub async fn get_current_pregame(
+    http: State<'_, HttpState>,
+    remote: State<'_, RemoteState>, lockfile: State<'_, LockfileState>,
+    uuid: Option<String>
+) -> Result<PreGamePlayerResponse, RiotError> {
+    let client = RemoteClient::new(&http, &remote, &lockfile);
+    let uuid = match uuid {
+        Some(uuid) => uuid,
+        None => client.puuid()?
+    };
+    client.current_pregame(&uuid).await
+}
+
+#[tauri::command]
+pub async fn get_pregame_data(
+    http: State<'_, HttpState>,
+    remote: State<'_, RemoteState>, lockfile: State<'_, LockfileState>,
+    pregame_id: String
+) -> Result<PreGameMatchResponse, RiotError> {
+    RemoteClient::new(&http, &remote, &lockfile).pregame_data(&pregame_id).await
+}
+
+#[tauri::command]
+pub async fn get_match_history(
+    http: State<'_, HttpState>,
+    remote: State<'_, RemoteState>, lockfile: State<'_, LockfileState>,
+    uuid: Option<String>,
+    start_index: Option<u32>, end_index: Option<u32>, queue: Option<String>
+) -> Result<MatchHistoryResponse, RiotError> {
+    let client = RemoteClient::new(&http, &remote, &lockfile);
+    let uuid = match uuid {
+        Some(uuid) => uuid,
+        None => client.puuid()?
+    };
+    client.match_history(
+        &uuid,
+        start_index.unwrap_or(0),
+        end_index.unwrap_or(20),
+        &queue.unwrap_or_else(|| "competitive".to_string())).await
+}
+
+#[tauri::command]
+pub async fn get_match_data(
+    http: State<'_, HttpState>,
+    remote: State<'_, RemoteState>, lockfile: State<'_, LockfileState>,
+    match_id: String
+) -> Result<MatchDetailsResponse, RiotError> {
+    RemoteClient::new(&http, &remote, &lockfile).match_data(&match_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+    use crate::testing::{self, TestServer};
+
+    fn session(base_url: Option<String>) -> RemoteSession {
+        RemoteSession {
+            region: "na".to_string(),
+            shard: "na".to_string(),
+            client_version: "release-08.07-shipping-9-2444158".to_string(),
+            access_token: "old-access".to_string(),
+            entitlement_token: "old-entitlement".to_string(),
+            puuid: "puuid".to_string(),
+            base_url
+        }
+    }
+
+    /// Serves the local API and the remote API on one port.
+    /// The local API hands out new tokens; the remote API accepts `token`.
+    async fn serve(token: &'static str) -> TestServer {
+        TestServer::start(Some(testing::tls_acceptor()), move |head| {
+            if head.starts_with("GET /entitlements/v1/token ") {
+                let body = json!({
+                    "accessToken": "new-access",
+                    "entitlements": [],
+                    "issuer": "https://entitlements.auth.riotgames.com",
+                    "subject": "puuid",
+                    "token": "new-entitlement"
+                });
+                return testing::response(200, &[], &body.to_string());
+            }
+
+            let authorization = testing::header(head, "Authorization");
+            if authorization != Some(format!("Bearer {}", token)) {
+                return testing::response(401, &[], r#"{"errorCode":"BAD_CLAIMS"}"#);
+            }
+            let body = json!({ "Subject": "puuid", "MatchID": "match", "Version": 1 });
+            testing::response(200, &[], &body.to_string())
+        }).await
+    }
+
+    /// Creates the app state for a server from `serve`, holding expired tokens.
+    fn state(server: &TestServer) -> (HttpState, RemoteState, LockfileState) {
+        let http = HttpState::new(None).unwrap();
+        http.set_local_port(Some(server.port));
+        let base_url = format!("https://127.0.0.1:{}", server.port);
+        let remote = RemoteState(Mutex::new(Some(session(Some(base_url)))));
+        let lockfile = LockfileState(Mutex::new(Some(testing::lockfile(server.port))));
+
+        (http, remote, lockfile)
+    }
+
+    /// Returns the request lines received by a server.
+    fn request_lines(server: &TestServer) -> Vec<String> {
+        server.requests().iter()
+            .map(|head| head.lines().next().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn base_url_uses_the_region_and_shard() {
+        let remote = session(None);
+        assert_eq!(remote.base_url(Host::Pd), "https://pd.na.a.pvp.net");
+        assert_eq!(remote.base_url(Host::Glz), "https://glz-na-1.na.a.pvp.net");
+
+        let remote = session(Some("https://127.0.0.1:1234".to_string()));
+        assert_eq!(remote.base_url(Host::Pd), "https://127.0.0.1:1234");
+        assert_eq!(remote.base_url(Host::Glz), "https://127.0.0.1:1234");
+    }
+
+    #[tokio::test]
+    async fn rejected_tokens_are_refreshed_once() {
+        let server = serve("new-access").await;
+        let (http, remote, lockfile) = state(&server);
+
+        let game = RemoteClient::new(&http, &remote, &lockfile).current_game("puuid").await.unwrap();
+        assert_eq!(game.match_id, "match");
+        assert_eq!(request_lines(&server), vec![
+            "GET /core-game/v1/players/puuid HTTP/1.1",
+            "GET /entitlements/v1/token HTTP/1.1",
+            "GET /core-game/v1/players/puuid HTTP/1.1"
+        ]);
+
+        // The retry and the stored session use the new tokens.
+        let retry = &server.requests()[2];
+        assert_eq!(testing::header(retry, "Authorization").as_deref(), Some("Bearer new-access"));
+        assert_eq!(testing::header(retry, "X-Riot-Entitlements-JWT").as_deref(), Some("new-entitlement"));
+        let session = remote.session().unwrap();
+        assert_eq!(session.access_token, "new-access");
+        assert_eq!(session.entitlement_token, "new-entitlement");
+    }
+
+    #[tokio::test]
+    async fn rejected_tokens_are_not_retried_twice() {
+        let server = serve("other-access").await;
+        let (http, remote, lockfile) = state(&server);
+
+        match RemoteClient::new(&http, &remote, &lockfile).current_game("puuid").await {
+            Err(RiotError::Status { status, .. }) => assert_eq!(status, 401),
+            other => panic!("expected a status error, got {:?}", other.map(|_| ()))
+        }
+        assert_eq!(request_lines(&server), vec![
+            "GET /core-game/v1/players/puuid HTTP/1.1",
+            "GET /entitlements/v1/token HTTP/1.1",
+            "GET /core-game/v1/players/puuid HTTP/1.1"
+        ]);
+    }
+
+    #[test]
+    fn match_history_is_decoded() {
+        let history: MatchHistoryResponse = serde_json::from_value(json!({
+            "Subject": "puuid",
+            "BeginIndex": 0,
+            "EndIndex": 1,
+            "Total": 42,
+            "History": [
+                { "MatchID": "match", "GameStartTime": 1712685600000i64, "QueueID": "competitive" }
+            ]
+        })).unwrap();
+
+        assert_eq!(history.total, 42);
+        assert_eq!(history.history[0].match_id, "match");
+        assert_eq!(history.history[0].game_start_time, 1712685600000);
+        assert_eq!(history.history[0].queue_id, "competitive");
+    }
+
+    #[test]
+    fn match_details_are_decoded() {
+        let details: MatchDetailsResponse = serde_json::from_value(json!({
+            "matchInfo": {
+                "matchId": "match",
+                "mapId": "/Game/Maps/Ascent/Ascent",
+                "gamePodId": "aresriot.aws-rclusterprod-use1-1.na-gp-ashburn-1",
+                "gameLoopZone": "na-gp-ashburn-1",
+                "gameServerAddress": "",
+                "gameVersion": "release-08.07-shipping-9-2444158",
+                "gameLengthMillis": null,
+                "gameStartMillis": 1712685600000i64,
+                "provisioningFlowID": "Matchmaking",
+                "isCompleted": true,
+                "customGameName": "",
+                "forcePostProcessing": false,
+                "queueID": "competitive",
+                "gameMode": "/Game/GameModes/Bomb/BombGameMode.BombGameMode_C",
+                "isRanked": true,
+                "isMatchSampled": false,
+                "seasonId": "season",
+                "completionState": "Completed",
+                "platformType": "PC",
+                "premierMatchInfo": {},
+                "partyRRPenalties": { "party": 0.0 },
+                "shouldMatchDisablePenalties": false
+            },
+            "players": [],
+            "bots": [],
+            "coaches": [{ "subject": "coach", "teamId": "Blue" }],
+            "teams": [{ "teamId": "Blue", "won": true, "roundsPlayed": 24, "roundsWon": 13, "numPoints": 13 }],
+            "roundResults": null,
+            "kills": null
+        })).unwrap();
+
+        assert_eq!(details.match_info.match_id, "match");
+        assert_eq!(details.match_info.provisioning_flow_id, "Matchmaking");
+        assert_eq!(details.match_info.queue_id, "competitive");
+        assert_eq!(details.match_info.party_rr_penalties.unwrap()["party"], 0.0);
+        assert_eq!(details.coaches[0].team_id, "Blue");
+        assert_eq!(details.teams.unwrap()[0].rounds_won, 13);
+        assert!(details.round_results.is_none());
+    }
+
+    #[test]
+    fn round_kills_omit_the_round() {
+        let kill = json!({
+            "gameTime": 61000,
+            "roundTime": 21000,
+            "killer": "killer",
+            "victim": "victim",
+            "victimLocation": { "x": 1.0, "y": 2.0 },
+            "assistants": [],
+            "playerLocations": [],
+            "finishingDamage": {
+                "damageType": "Weapon",
+                "damageItem": "weapon",
+                "isSecondaryFireMode": false
+            }
+        });
+
+        let round_kill: Kill = serde_json::from_value(kill.clone()).unwrap();
+        assert_eq!(round_kill.round, None);
+        assert_eq!(serde_json::to_value(&round_kill).unwrap(), kill);
+
+        let mut match_kill = kill;
+        match_kill["round"] = json!(3);
+        let match_kill: Kill = serde_json::from_value(match_kill).unwrap();
+        assert_eq!(match_kill.round, Some(3));
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use reqwest::StatusCode;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+use crate::lockfile::LockfileInfo;
+
+/// A local HTTP/1.1 server for tests.
+/// Connections are kept alive between requests.
+pub struct TestServer {
+    pub port: u16,
+    /// The number of TCP connections accepted.
+    connections: Arc<AtomicUsize>,
+    /// The head of every request received, in order.
+    requests: Arc<Mutex<Vec<String>>>
+}
+
+impl TestServer {
+    /// Starts a server on a random port, using TLS if an acceptor is given.
+    /// `respond` is given the request head and returns the whole raw response.
+    pub async fn start<F>(tls: Option<TlsAcceptor>, respond: F) -> Self
+    where F: Fn(&str) -> Vec<u8> + Send + Sync + 'static {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let respond = Arc::new(respond);
+
+        let (accepted, received) = (connections.clone(), requests.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+
+                let (tls, respond, received) = (tls.clone(), respond.clone(), received.clone());
+                tokio::spawn(async move {
+                    match tls {
+                        Some(tls) => {
+                            if let Ok(stream) = tls.accept(stream).await {
+                                serve(stream, &*respond, &received).await;
+                            }
+                        }
+                        None => serve(stream, &*respond, &received).await
+                    }
+                });
+            }
+        });
+
+        Self { port, connections, requests }
+    }
+
+    /// Returns the number of TCP connections accepted so far.
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    /// Returns the head of every request received, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Creates a TLS acceptor with a new self-signed certificate for `127.0.0.1`.
+pub fn tls_acceptor() -> TlsAcceptor {
+    let rcgen::CertifiedKey { cert, key_pair } =
+        rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+    let identity = native_tls::Identity::from_pkcs8(
+        cert.pem().as_bytes(), key_pair.serialize_pem().as_bytes()).unwrap();
+
+    TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap())
+}
+
+/// Creates a lockfile for a Riot Client on the given port, with the password `secret`.
+pub fn lockfile(port: u16) -> LockfileInfo {
+    LockfileInfo {
+        name: "Riot Client".to_string(),
+        pid: 1,
+        port,
+        password: "secret".to_string(),
+        protocol: "https".to_string()
+    }
+}
+
+/// Creates a raw response with the given status, extra headers and body.
+pub fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Vec<u8> {
+    let reason = StatusCode::from_u16(status).ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("");
+
+    let mut response = format!("HTTP/1.1 {} {}\r\nContent-Length: {}\r\n", status, reason, body.len());
+    for (name, value) in headers {
+        response += &format!("{}: {}\r\n", name, value);
+    }
+    response += "\r\n";
+    response += body;
+
+    response.into_bytes()
+}
+
+/// Returns the value of a header in a request head.
+/// Header names are case-insensitive.
+pub fn header(head: &str, name: &str) -> Option<String> {
+    head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Answers requests on a connection until it closes.
+async fn serve<S, F>(mut stream: S, respond: &F, requests: &Mutex<Vec<String>>)
+where S: AsyncRead + AsyncWrite + Unpin, F: Fn(&str) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    loop {
+        let head = match read_head(&mut stream, &mut buffer).await {
+            Some(head) => head,
+            None => return
+        };
+
+        // Skip the request body.
+        let length = header(&head, "Content-Length")
+            .and_then(|length| length.parse::<usize>().ok())
+            .unwrap_or(0);
+        while buffer.len() < length {
+            if !fill(&mut stream, &mut buffer).await {
+                return;
+            }
+        }
+        buffer.drain(..length);
+
+        requests.lock().unwrap().push(head.clone());
+        if stream.write_all(&respond(&head)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads up to the end of a request head.
+/// The head is removed from the buffer and returned.
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S, buffer: &mut Vec<u8>) -> Option<String> {
+    loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head: Vec<u8> = buffer.drain(..end + 4).collect();
+            return Some(String::from_utf8_lossy(&head).to_string());
+        }
+        if !fill(stream, buffer).await {
+            return None;
+        }
+    }
+}
+
+/// Reads more bytes into the buffer.
+/// Returns false once the connection is closed.
+async fn fill<S: AsyncRead + Unpin>(stream: &mut S, buffer: &mut Vec<u8>) -> bool {
+    let mut chunk = [0u8; 4096];
+    match stream.read(&mut chunk).await {
+        Ok(0) | Err(_) => false,
+        Ok(read) => {
+            buffer.extend_from_slice(&chunk[..read]);
+            true
+        }
+    }
+}
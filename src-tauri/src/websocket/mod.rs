@@ -0,0 +1,534 @@
+mod wamp;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::Future;
+use std::iter;
+use std::time::Duration;
+use std::sync::Mutex;
+use base64::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use native_tls::TlsConnector;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tauri::async_runtime::JoinHandle;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use crate::lockfile::{self, LockfileError, LockfileInfo, LockfileState};
+use crate::riot::presence;
+
+pub use self::wamp::{EventType, JsonApiEvent};
+
+pub type LocalSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The delay before the first reconnect attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The longest delay between reconnect attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The state of the local websocket connection.
+/// Emitted to the webview as `ws-state` whenever it changes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// Waiting to reconnect.
+    Disconnected {
+        #[serde(rename = "retryInMs")]
+        retry_in_ms: u64
+    },
+    Connecting { attempt: u32 },
+    Connected
+}
+
+/// The local websocket connection, kept in Tauri managed state.
+#[derive(Default)]
+pub struct SocketState {
+    /// The task keeping the connection alive, if started.
+    task: Mutex<Option<JoinHandle<()>>>,
+    /// Topics subscribed to on every connection.
+    topics: Mutex<BTreeSet<String>>,
+    /// Sends messages over the current connection, if connected.
+    sender: Mutex<Option<UnboundedSender<Message>>>,
+    state: Mutex<ConnectionState>,
+    /// Event URI prefixes requested with `ws_subscribe`, with their subscriber count.
+    prefixes: Mutex<BTreeMap<String, usize>>
+}
+
+impl SocketState {
+    /// Adds a subscriber to a URI prefix.
+    /// Returns the event channel the matching events are emitted on.
+    fn subscribe(&self, prefix: String) -> String {
+        self.request_topic(wamp::JSON_API_EVENT);
+        let channel = channel(&prefix);
+        *self.prefixes.lock().unwrap().entry(prefix).or_insert(0) += 1;
+
+        channel
+    }
+
+    /// Removes a subscriber from a URI prefix.
+    /// Returns false if the prefix had no subscribers.
+    fn unsubscribe(&self, prefix: &str) -> bool {
+        let mut prefixes = self.prefixes.lock().unwrap();
+        match prefixes.get_mut(prefix) {
+            Some(count) if *count > 1 => *count -= 1,
+            Some(_) => {
+                prefixes.remove(prefix);
+            }
+            None => return false
+        }
+
+        true
+    }
+
+    /// Returns the channel of every subscribed prefix of a URI.
+    fn channels(&self, uri: &str) -> Vec<String> {
+        self.prefixes.lock().unwrap().keys()
+            .filter(|prefix| uri.starts_with(prefix.as_str()))
+            .map(|prefix| channel(prefix))
+            .collect()
+    }
+
+    /// Subscribes to a WAMP topic now, if connected, and on every later connection.
+    fn request_topic(&self, topic: &str) {
+        if !self.topics.lock().unwrap().insert(topic.to_string()) {
+            return;
+        }
+
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(Message::Text(wamp::subscribe(topic)));
+        }
+    }
+
+    /// Updates the connection state and emits `ws-state`.
+    fn set_state(&self, app: &AppHandle, state: ConnectionState) {
+        *self.state.lock().unwrap() = state.clone();
+        if let Err(error) = app.emit_all("ws-state", state) {
+            warn!("Unable to emit 'ws-state': {}", error);
+        }
+    }
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        ConnectionState::Disconnected { retry_in_ms: 0 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum SocketError {
+    /// The Riot Client lockfile could not be used.
+    Lockfile(LockfileError),
+    /// The websocket connection could not be opened.
+    Connect(String)
+}
+
+impl fmt::Display for SocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SocketError::Lockfile(error) => write!(f, "{}", error),
+            SocketError::Connect(error) => write!(f, "Failed to connect to the local websocket: {}", error)
+        }
+    }
+}
+
+impl From<LockfileError> for SocketError {
+    fn from(error: LockfileError) -> Self {
+        SocketError::Lockfile(error)
+    }
+}
+
+/// Opens a websocket to the local Riot Client.
+/// This never goes through the proxy, as `127.0.0.1` is always connected to directly.
+/// The client serves a self-signed certificate, so verification
+/// is skipped for this connection only.
+pub async fn connect(info: &LockfileInfo) -> Result<LocalSocket, SocketError> {
+    let request = format!("wss://127.0.0.1:{}", info.port).into_client_request();
+    let mut request = match request {
+        Ok(request) => request,
+        Err(error) => return Err(SocketError::Connect(error.to_string()))
+    };
+
+    let authorization = format!("Basic {}",
+        BASE64_STANDARD.encode(format!("riot:{}", info.password)));
+    request.headers_mut().insert("Authorization", HeaderValue::from_str(&authorization).unwrap());
+
+    let connector = TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .build();
+    let connector = match connector {
+        Ok(connector) => connector,
+        Err(error) => return Err(SocketError::Connect(error.to_string()))
+    };
+
+    let stream = match TcpStream::connect(("127.0.0.1", info.port)).await {
+        Ok(stream) => stream,
+        Err(error) => return Err(SocketError::Connect(error.to_string()))
+    };
+
+    let result = tokio_tungstenite::client_async_tls_with_config(
+        request, stream, None, Some(Connector::NativeTls(connector))).await;
+    match result {
+        Ok((socket, _)) => Ok(socket),
+        Err(error) => Err(SocketError::Connect(error.to_string()))
+    }
+}
+
+/// Forwards a message from the local websocket to the webview.
+fn handle_message(app: &AppHandle, text: &str) {
+    let (topic, payload) = match wamp::decode_event(text) {
+        Some(event) => event,
+        None => return
+    };
+    if topic != wamp::JSON_API_EVENT {
+        return;
+    }
+
+    let event = match wamp::decode_json_api_event(payload) {
+        Some(event) => event,
+        None => {
+            warn!("Received a malformed {} event.", topic);
+            return;
+        }
+    };
+
+    if event.uri == presence::PRESENCES_URI {
+        presence::handle(app, &event);
+    }
+
+    // Forward the event to every subscription with a matching prefix.
+    let channels = app.state::<SocketState>().channels(&event.uri);
+    for name in channels.iter().map(String::as_str).chain(iter::once("riot-event")) {
+        if let Err(error) = app.emit_all(name, event.clone()) {
+            warn!("Unable to emit '{}': {}", name, error);
+        }
+    }
+}
+
+/// Returns the event channel for a URI prefix, e.g. `riot-event:/chat/v4/presences`.
+/// Characters Tauri does not allow in event names, and `_` itself, are written
+/// as `_` followed by the hex of each UTF-8 byte, so every prefix has its own channel.
+fn channel(prefix: &str) -> String {
+    let mut channel = "riot-event:".to_string();
+    for byte in prefix.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'/' | b':' => channel.push(byte as char),
+            _ => channel += &format!("_{:02x}", byte)
+        }
+    }
+    channel
+}
+
+/// Delays between reconnect attempts, doubling up to a maximum.
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    delay: Duration
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max, delay: initial }
+    }
+
+    /// Returns the next delay, and doubles the one after it.
+    fn next(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (delay * 2).min(self.max);
+        delay
+    }
+
+    /// Starts again from the initial delay.
+    fn reset(&mut self) {
+        self.delay = self.initial;
+    }
+}
+
+/// Reads messages from a connection until it closes.
+/// Every requested topic is subscribed to first.
+async fn session<M: FnMut(&str)>(socket: &SocketState, mut stream: LocalSocket, on_message: &mut M) {
+    // Publish the sender before reading the topics, so topics
+    // requested in between are subscribed to at least once.
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    *socket.sender.lock().unwrap() = Some(sender);
+
+    let topics: Vec<String> = socket.topics.lock().unwrap().iter().cloned().collect();
+    for topic in topics {
+        if let Err(error) = stream.send(Message::Text(wamp::subscribe(&topic))).await {
+            warn!("Unable to subscribe to {}: {}", topic, error);
+        }
+    }
+
+    loop {
+        tokio::select! {
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => on_message(&text),
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(error)) => {
+                    warn!("Local websocket error: {}", error);
+                    break;
+                }
+            },
+            Some(message) = receiver.recv() => {
+                if let Err(error) = stream.send(message).await {
+                    warn!("Local websocket error: {}", error);
+                    break;
+                }
+            }
+        }
+    }
+
+    *socket.sender.lock().unwrap() = None;
+}
+
+/// Keeps a connection open, starting with `stream` if given.
+/// After a disconnect, reconnects with `connect` after the next backoff delay.
+async fn supervise<C, F, M, S>(
+    socket: &SocketState,
+    mut stream: Option<LocalSocket>,
+    mut backoff: Backoff,
+    mut connect: C,
+    mut on_message: M,
+    mut on_state: S
+) where
+    C: FnMut() -> F,
+    F: Future<Output = Result<LocalSocket, SocketError>>,
+    M: FnMut(&str),
+    S: FnMut(ConnectionState)
+{
+    let mut attempt = 0;
+    loop {
+        let connected = match stream.take() {
+            Some(stream) => Ok(stream),
+            None => {
+                attempt += 1;
+                on_state(ConnectionState::Connecting { attempt });
+                connect().await
+            }
+        };
+
+        match connected {
+            Ok(stream) => {
+                info!("Connected to the local websocket.");
+                on_state(ConnectionState::Connected);
+                attempt = 0;
+                backoff.reset();
+
+                session(socket, stream, &mut on_message).await;
+                info!("Local websocket closed.");
+            }
+            Err(error) => warn!("{}", error)
+        }
+
+        let delay = backoff.next();
+        on_state(ConnectionState::Disconnected {
+            retry_in_ms: delay.as_millis() as u64
+        });
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Keeps the local websocket connected.
+/// After a disconnect, reconnects with exponential backoff,
+/// reading fresh credentials from the lockfile each time.
+async fn run(app: AppHandle, stream: Option<LocalSocket>) {
+    let socket = app.state::<SocketState>();
+    let reconnect = || async {
+        match lockfile::read() {
+            Ok(info) => connect(&info).await,
+            Err(error) => Err(error.into())
+        }
+    };
+
+    supervise(&socket, stream, Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF), reconnect,
+        |text| handle_message(&app, text),
+        |state| socket.set_state(&app, state)).await;
+}
+
+/// Connects to the local websocket, replacing any previous connection.
+/// Every local API event is forwarded to the webview as `riot-event`.
+/// The connection is kept alive until the app exits; see `ws-state` for its state.
+#[tauri::command]
+pub async fn ws_connect(
+    app: AppHandle,
+    lockfile: State<'_, LockfileState>,
+    socket: State<'_, SocketState>
+) -> Result<(), SocketError> {
+    let stream = connect(&lockfile.current()?).await?;
+    socket.request_topic(wamp::JSON_API_EVENT);
+
+    let task = tauri::async_runtime::spawn(run(app, Some(stream)));
+    if let Some(previous) = socket.task.lock().unwrap().replace(task) {
+        previous.abort();
+    }
+
+    Ok(())
+}
+
+/// Subscribes to local API events whose URI starts with the given prefix.
+/// Returns the event channel the matching events are emitted on.
+#[tauri::command]
+pub fn ws_subscribe(socket: State<'_, SocketState>, topic: String) -> String {
+    socket.subscribe(topic)
+}
+
+/// Removes a subscription made with `ws_subscribe`.
+/// Events stop being emitted on the channel once every subscriber has unsubscribed.
+/// Returns false if there was no subscription to the prefix.
+#[tauri::command]
+pub fn ws_unsubscribe(socket: State<'_, SocketState>, topic: String) -> bool {
+    socket.unsubscribe(&topic)
+}
+
+/// Returns the state of the local websocket connection.
+#[tauri::command]
+pub fn ws_state(socket: State<'_, SocketState>) -> ConnectionState {
+    socket.state.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn channels_are_unique_per_prefix() {
+        assert_eq!(channel("/chat/v4/presences"), "riot-event:/chat/v4/presences");
+        assert_eq!(channel("/a.b"), "riot-event:/a_2eb");
+        assert_eq!(channel("/a_b"), "riot-event:/a_5fb");
+        assert_eq!(channel("/a_2eb"), "riot-event:/a_5f2eb");
+        assert_eq!(channel("/é"), "riot-event:/_c3_a9");
+    }
+
+    #[test]
+    fn subscriptions_are_counted() {
+        let socket = SocketState::default();
+        assert_eq!(socket.subscribe("/chat/".to_string()), "riot-event:/chat/");
+        assert_eq!(socket.subscribe("/chat/".to_string()), "riot-event:/chat/");
+        assert!(socket.topics.lock().unwrap().contains(wamp::JSON_API_EVENT));
+
+        assert!(socket.unsubscribe("/chat/"));
+        assert_eq!(socket.channels("/chat/v4/presences"), vec!["riot-event:/chat/"]);
+        assert!(socket.unsubscribe("/chat/"));
+        assert!(socket.channels("/chat/v4/presences").is_empty());
+        assert!(!socket.unsubscribe("/chat/"));
+    }
+
+    #[test]
+    fn events_go_to_matching_prefixes_only() {
+        let socket = SocketState::default();
+        for prefix in ["/chat/", "/chat/v4/presences", "/a.b", "/a_b", "/product-session/"] {
+            socket.subscribe(prefix.to_string());
+        }
+
+        assert_eq!(socket.channels("/chat/v4/presences"),
+            vec!["riot-event:/chat/", "riot-event:/chat/v4/presences"]);
+        assert_eq!(socket.channels("/chat/v6/messages"), vec!["riot-event:/chat/"]);
+        assert_eq!(socket.channels("/a.b/c"), vec!["riot-event:/a_2eb"]);
+        assert_eq!(socket.channels("/a_b/c"), vec!["riot-event:/a_5fb"]);
+        assert!(socket.channels("/riot-messaging-service/v1/message").is_empty());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn supervise_reconnects_and_resubscribes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let info = testing::lockfile(listener.local_addr().unwrap().port());
+        let tls = testing::tls_acceptor();
+
+        // Accepts two connections, closing each after its first message.
+        // The listener is then dropped, so later attempts are refused.
+        let server = tokio::spawn(async move {
+            let mut received = Vec::new();
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let stream = tls.accept(stream).await.unwrap();
+                let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+                if let Some(Ok(Message::Text(text))) = stream.next().await {
+                    received.push(text);
+                }
+                stream.close(None).await.unwrap();
+            }
+
+            received
+        });
+
+        let socket = SocketState::default();
+        socket.request_topic(wamp::JSON_API_EVENT);
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let supervisor = supervise(&socket, None,
+            Backoff::new(Duration::from_millis(10), Duration::from_secs(1)),
+            || connect(&info),
+            |_| {},
+            |state| sender.send(state).unwrap());
+
+        // Collects the retry delays until the fourth disconnect.
+        let delays = async {
+            let mut delays = Vec::new();
+            while delays.len() < 4 {
+                if let Some(ConnectionState::Disconnected { retry_in_ms }) = receiver.recv().await {
+                    delays.push(retry_in_ms);
+                }
+            }
+            delays
+        };
+
+        let delays = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::select! {
+                _ = supervisor => unreachable!(),
+                delays = delays => delays
+            }
+        }).await.unwrap();
+
+        // Each connection resets the backoff; each refused attempt doubles it.
+        assert_eq!(delays, vec![10, 10, 20, 40]);
+        assert_eq!(server.await.unwrap(), vec![r#"[5,"OnJsonApiEvent"]"#.to_string(); 2]);
+    }
+
+    #[tokio::test]
+    async fn connect_accepts_the_self_signed_certificate() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let tls = testing::tls_acceptor();
+
+        // Accepts one connection and returns its Authorization header.
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let stream = tls.accept(stream).await.unwrap();
+
+            let mut authorization = None;
+            let callback = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+                authorization = request.headers().get("Authorization")
+                    .map(|value| value.to_str().unwrap().to_string());
+                Ok(response)
+            };
+            tokio_tungstenite::accept_hdr_async(stream, callback).await.unwrap();
+
+            authorization
+        });
+
+        if let Err(error) = connect(&testing::lockfile(port)).await {
+            panic!("{}", error);
+        }
+        assert_eq!(server.await.unwrap().as_deref(), Some("Basic cmlvdDpzZWNyZXQ="));
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Subscribes to a topic: `[5, topic]`.
+const SUBSCRIBE: u8 = 5;
+/// An event published to a topic: `[8, topic, payload]`.
+const EVENT: u8 = 8;
+
+/// The topic carrying every local API event.
+pub const JSON_API_EVENT: &str = "OnJsonApiEvent";
+
+/// What happened to the resource at an event's URI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EventType {
+    Create,
+    Update,
+    Delete
+}
+
+/// An event from the `OnJsonApiEvent` topic.
+/// Emitted to the webview as `riot-event`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonApiEvent {
+    /// The local API path of the resource, e.g. `/chat/v4/presences`.
+    pub uri: String,
+    pub event_type: EventType,
+    /// The new state of the resource; empty for deletions.
+    #[serde(default)]
+    pub data: Value
+}
+
+/// Creates a subscribe message for a topic.
+pub fn subscribe(topic: &str) -> String {
+    json!([SUBSCRIBE, topic]).to_string()
+}
+
+/// Decodes an event message into its topic and payload.
+/// Returns none for any other message.
+pub fn decode_event(text: &str) -> Option<(String, Value)> {
+    let message: Vec<Value> = serde_json::from_str(text).ok()?;
+    match message.as_slice() {
+        [opcode, topic, payload] if opcode.as_u64() == Some(EVENT as u64) =>
+            Some((topic.as_str()?.to_string(), payload.clone())),
+        _ => None
+    }
+}
+
+/// Decodes the payload of an `OnJsonApiEvent` event.
+pub fn decode_json_api_event(payload: Value) -> Option<JsonApiEvent> {
+    serde_json::from_value(payload).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribe_names_the_topic() {
+        assert_eq!(subscribe(JSON_API_EVENT), r#"[5,"OnJsonApiEvent"]"#);
+    }
+
+    #[test]
+    fn decode_event_reads_events_only() {
+        let (topic, payload) = decode_event(r#"[8,"OnJsonApiEvent",{"uri":"/help"}]"#).unwrap();
+        assert_eq!(topic, JSON_API_EVENT);
+        assert_eq!(payload, json!({ "uri": "/help" }));
+
+        assert_eq!(decode_event(r#"[5,"OnJsonApiEvent"]"#), None);
+        assert_eq!(decode_event(r#"[0,"session",1,"server"]"#), None);
+        assert_eq!(decode_event(r#"[7,"OnJsonApiEvent",{}]"#), None);
+        assert_eq!(decode_event(r#"[8,1,{}]"#), None);
+        assert_eq!(decode_event(r#"{"opcode":8}"#), None);
+        assert_eq!(decode_event(""), None);
+    }
+
+    #[test]
+    fn decode_json_api_event_reads_every_event_type() {
+        let event = decode_json_api_event(json!({
+            "uri": "/chat/v4/presences",
+            "eventType": "Update",
+            "data": { "presences": [] }
+        })).unwrap();
+        assert_eq!(event.uri, "/chat/v4/presences");
+        assert_eq!(event.event_type, EventType::Update);
+        assert_eq!(event.data, json!({ "presences": [] }));
+
+        let event = decode_json_api_event(json!({ "uri": "/chat/v4/presences", "eventType": "Create" })).unwrap();
+        assert_eq!(event.event_type, EventType::Create);
+        assert_eq!(event.data, Value::Null);
+
+        let event = decode_json_api_event(json!({ "uri": "/chat/v4/presences", "eventType": "Delete" })).unwrap();
+        assert_eq!(event.event_type, EventType::Delete);
+    }
+
+    #[test]
+    fn decode_json_api_event_rejects_malformed_events() {
+        assert!(decode_json_api_event(json!({ "uri": "/help", "eventType": "Patch" })).is_none());
+        assert!(decode_json_api_event(json!({ "uri": "/help" })).is_none());
+        assert!(decode_json_api_event(json!({ "eventType": "Update" })).is_none());
+        assert!(decode_json_api_event(json!("/help")).is_none());
+    }
+}
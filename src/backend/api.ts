@@ -1,15 +1,6 @@
 import { info, error } from "tauri-plugin-log-api";
-import WebSocket from "tauri-plugin-websocket-api";
-import { localDataDir } from "@tauri-apps/api/path";
-import { readTextFile, exists } from "@tauri-apps/api/fs";
-
-import fetch, { type RequestOptions } from "@backend/fetch.ts";
-
-/// <editor-fold defaultstate="collapsed" desc="Global Constants">
-const clientPlatform = "ew0KCSJwbGF0Zm9ybVR5cGUiOiAiUEMiLA0KCSJwbGF0Zm9ybU9TIjogIldpbmRvd3MiLA0KCSJwbGF0Zm9ybU9TVmVyc2lvbiI6ICIxMC4wLjE5MDQyLjEuMjU2LjY0Yml0IiwNCgkicGxhdGZvcm1DaGlwc2V0IjogIlVua25vd24iDQp9";
-const lockFilePath = `${await localDataDir()}Riot Games/Riot Client/Config/lockfile`;
-const logFilePath = `${await localDataDir()}VALORANT/Saved/Logs/ShooterGame.log`;
-/// </editor-fold>
+import { invoke } from "@tauri-apps/api";
+import { listen } from "@tauri-apps/api/event";
 
 /**
  * Terminology:
@@ -34,7 +25,7 @@ export default class API {
     public static entitlementToken: string;
 
     // WebSocket connection.
-    public static socket: WebSocket;
+    public static socketConnected = false;
 
     // <editor-fold defaultstate="collapsed" desc="Setup Methods">
 
@@ -42,36 +33,35 @@ export default class API {
      * Parses the local lock file and gets the API information.
      */
     public static async parseLockFile(): Promise<boolean> {
-        if (!await exists(lockFilePath)) {
-            await info("'lockfile' does not exist. Is VALORANT open?");
+        try {
+            const lockfile = await invoke("lockfile") as LockfileInfo;
+            API.apiInfo = {
+                username: lockfile.name,
+                processId: lockfile.pid,
+                port: lockfile.port,
+                password: lockfile.password,
+                protocol: lockfile.protocol
+            };
+        } catch (error) {
+            await info(`Unable to read 'lockfile': ${JSON.stringify(error)}`);
             return false;
         }
 
-        const text = await readTextFile(lockFilePath);
-        const [username, processId, port, password, protocol] = text.split(":");
-        API.apiInfo = { username, processId: +processId, port: +port, password, protocol };
-
         return true;
     }
 
     /**
-     * Parses the local log file and gets the remote API information.
+     * Gets the remote API information found in the game log.
      */
-    public static async parseLogFile(): Promise<boolean> {
-        if (!await exists(logFilePath)) {
-            await info("'ShooterGame.log' does not exist. Is VALORANT open?");
+    public static async readGameLog(): Promise<boolean> {
+        const log = await invoke("game_log") as GameLogInfo;
+        if (!log.region || !log.shard) {
+            await info("Region and shard not found in 'ShooterGame.log'. Is VALORANT open?");
             return false;
         }
 
-        const text = await readTextFile(logFilePath);
-        const match = text.match("https://glz-(.+?)-1.(.+?).a.pvp.net");
-        if (!match) {
-            await error("Failed to find the region and shard.");
-            return false;
-        }
-
-        API.region = match[1];
-        API.shard = match[2];
+        API.region = log.region;
+        API.shard = log.shard;
 
         return true;
     }
@@ -87,14 +77,12 @@ export default class API {
 
         await info("Connecting to local websocket...");
         try {
-            API.socket = await WebSocket.connect(
-                `wss://127.0.0.1:${API.apiInfo.port}`,
-                { headers: { Authorization: `Basic ${btoa(`riot:${API.apiInfo.password}`)}` } }
-            );
+            await invoke("ws_connect");
         } catch {
             return false;
         }
 
+        API.socketConnected = true;
         return true;
     }
 
@@ -105,7 +93,7 @@ export default class API {
         if (!API.apiInfo && !await API.parseLockFile()) {
             return false;
         }
-        if (!API.region && !await API.parseLogFile()) {
+        if (!API.region && !await API.readGameLog()) {
             return false;
         }
 
@@ -127,6 +115,14 @@ export default class API {
         }
         API.clientVersion = valorantSession.version;
 
+        // Set up the remote API session.
+        try {
+            await invoke("remote_setup", { region: API.region, shard: API.shard });
+        } catch (err) {
+            await error(`Failed to set up the remote API: ${JSON.stringify(err)}`);
+            return false;
+        }
+
         // Connect to the local API socket.
         if (!await API.connectToSocket()) {
             await error("Failed to connect to the local API socket.");
@@ -137,64 +133,28 @@ export default class API {
         return true;
     }
 
-    /// </editor-fold>
-
-    /// <editor-fold defaultstate="collapsed" desc="Request Methods">
-
     /**
-     * Performs a local API request.
-     *
-     * @param path The path to the endpoint.
-     * @param requestInfo The request information.
-     * @private
+     * Re-runs the client setup whenever the Riot Client restarts.
      */
-    private static async localRequest<T>(path: string, requestInfo?: RequestOptions): Promise<T> {
-        const response = await fetch(`${API.apiInfo.protocol}://127.0.0.1:${API.apiInfo.port}/${path}`, {
-            headers: { Authorization: `Basic ${btoa(`riot:${API.apiInfo.password}`)}` },
-            ...(requestInfo as any)
+    public static async watchClient(): Promise<void> {
+        await listen<LockfileInfo>("riot-client-started", async () => {
+            await info("Riot Client started; setting up API client...");
+            API.apiInfo = undefined as any;
+            await API.setupClient();
         });
-
-        if (response.status != 200) {
-            await error(`Client did not return a good response: ${response.status} ${response.body}`);
-            throw new Error("Failed to fetch data.");
-        }
-
-        return JSON.parse(response.body) as T;
-    }
-
-    /**
-     * Performs a remote API request.
-     *
-     * @param path The path to the endpoint.
-     * @param requestInfo The request information.
-     * @param args Additional arguments.
-     * @private
-     */
-    private static async remoteRequest<T>(
-        path: string, requestInfo?: RequestOptions,
-        args?: {
-            query?: "pd" | "glz"
-        }
-    ): Promise<T> {
-        const baseUrl = args?.query == "pd" ?
-            `https://pd.${API.shard}.a.pvp.net` :
-            `https://glz-${API.region}-1.${API.shard}.a.pvp.net`;
-        const response = await fetch(`${baseUrl}/${path}`, {
-            headers: {
-                "X-Riot-ClientPlatform": clientPlatform,
-                "X-Riot-ClientVersion": API.clientVersion,
-                "X-Riot-Entitlements-JWT": API.entitlementToken,
-                "Authorization": `Bearer ${API.authToken}`
-            },
-            ...(requestInfo as any)
+        await listen<LockfileInfo>("riot-client-stopped", async () => {
+            await info("Riot Client stopped.");
+            API.apiInfo = undefined as any;
+        });
+        await listen<ConnectionState>("ws-state", ({ payload }) => {
+            API.socketConnected = payload.state == "connected";
+        });
+        await listen<GameLogEvent>("game-log", ({ payload }) => {
+            if (payload.type == "region") {
+                API.region = payload.region;
+                API.shard = payload.shard;
+            }
         });
-
-        if (response.status != 200) {
-            await error(`Server did not return a good response: ${response.status} ${response.body}`);
-            throw new Error("Failed to fetch data.");
-        }
-
-        return JSON.parse(response.body) as T;
     }
 
     /// </editor-fold>
@@ -205,14 +165,14 @@ export default class API {
      * Fetches the entitlements token for the player.
      */
     public static async getEntitlementsToken(): Promise<EntitlementsTokenResponse> {
-        return await API.localRequest("entitlements/v1/token");
+        return await invoke("get_entitlements_token") as EntitlementsTokenResponse;
     }
 
     /**
      * Fetches the active chat session for the player.
      */
     public static async getChatSession(): Promise<ChatSessionResponse> {
-        return await API.localRequest("chat/v1/session");
+        return await invoke("get_chat_session") as ChatSessionResponse;
     }
 
     /**
@@ -220,14 +180,14 @@ export default class API {
      * This includes: VALORANT, League of Legends, and Riot Client.
      */
     public static async getSessions(): Promise<SessionsResponse> {
-        return await API.localRequest("product-session/v1/sessions");
+        return await invoke("get_sessions") as SessionsResponse;
     }
 
     /**
      * Fetches the help information for the local API.
      */
     public static async getHelp(): Promise<LocalHelpResponse> {
-        return await API.localRequest("help");
+        return await invoke("get_help") as LocalHelpResponse;
     }
 
     /// </editor-fold>
@@ -240,7 +200,7 @@ export default class API {
      * @param uuid The player UUID.
      */
     public static async getCurrentGame(uuid = API.playerUuid): Promise<CurrentGamePlayerResponse> {
-        return await API.remoteRequest(`core-game/v1/players/${uuid}`);
+        return await invoke("get_current_game", { uuid }) as CurrentGamePlayerResponse;
     }
 
     /**
@@ -249,7 +209,7 @@ export default class API {
      * @param gameId The game ID.
      */
     public static async getGameData(gameId: string): Promise<CurrentGameMatchResponse> {
-        return await API.remoteRequest(`core-game/v1/matches/${gameId}`);
+        return await invoke("get_game_data", { gameId }) as CurrentGameMatchResponse;
     }
 
     /**
@@ -258,7 +218,7 @@ export default class API {
      * @param uuid The player UUID.
      */
     public static async getCurrentPregame(uuid = API.playerUuid): Promise<PreGamePlayerResponse> {
-        return await API.remoteRequest(`pregame/v1/players/${uuid}`);
+        return await invoke("get_current_pregame", { uuid }) as PreGamePlayerResponse;
     }
 
     /**
@@ -267,7 +227,7 @@ export default class API {
      * @param pregameId The pre-game ID.
      */
     public static async getPregameData(pregameId: string): Promise<PreGameMatchResponse> {
-        return await API.remoteRequest(`pregame/v1/matches/${pregameId}`);
+        return await invoke("get_pregame_data", { pregameId }) as PreGameMatchResponse;
     }
 
     /**
@@ -284,15 +244,12 @@ export default class API {
             queue?: "competitive" | "unrated" | string;
         }
     ): Promise<MatchHistoryResponse> {
-        let params = new URLSearchParams();
-        params.append("startIndex", args?.startIndex?.toString() ?? "0");
-        params.append("endIndex", args?.endIndex?.toString() ?? "20");
-        params.append("queue", args?.queue ?? "competitive");
-
-        return await API.remoteRequest(
-            `match-history/v1/history/${playerUuid}?${params.toString()}`,
-            undefined, { query: "pd" }
-        );
+        return await invoke("get_match_history", {
+            uuid: playerUuid,
+            startIndex: args?.startIndex,
+            endIndex: args?.endIndex,
+            queue: args?.queue
+        }) as MatchHistoryResponse;
     }
 
     /**
@@ -300,15 +257,73 @@ export default class API {
      * @param matchId
      */
     public static async getMatchData(matchId: string): Promise<MatchDetailsResponse> {
-        return await API.remoteRequest(
-            `match-details/v1/matches/${matchId}`,
-            undefined, { query: "pd" }
-        );
+        return await invoke("get_match_data", { matchId }) as MatchDetailsResponse;
     }
 
     /// </editor-fold>
 
     /// <editor-fold defaultstate="collapsed" desc="WebSocket API">
+
+    /**
+     * Listens for events from the local API socket.
+     *
+     * @param callback Called with each event.
+     * @return A function which stops listening.
+     */
+    public static async onEvent(callback: (event: RiotEvent) => void): Promise<() => void> {
+        return await listen<RiotEvent>("riot-event", ({ payload }) => callback(payload));
+    }
+
+    /**
+     * Listens for changes to the VALORANT presence of any player.
+     *
+     * @param callback Called with each change.
+     * @return A function which stops listening.
+     */
+    public static async onPresenceChanged(callback: (change: PresenceChanged) => void): Promise<() => void> {
+        return await listen<PresenceChanged>("presence-changed", ({ payload }) => callback(payload));
+    }
+
+    /**
+     * Fetches the current game phase.
+     */
+    public static async getGamePhase(): Promise<GamePhase> {
+        return await invoke("game_phase") as GamePhase;
+    }
+
+    /**
+     * Listens for game phase transitions.
+     *
+     * @param callback Called with the previous and current phase.
+     * @return A function which stops listening.
+     */
+    public static async onGamePhase(
+        callback: (previous: GamePhase, current: GamePhase) => void
+    ): Promise<() => void> {
+        return await listen<{ previous: GamePhase; current: GamePhase }>("game-phase",
+            ({ payload }) => callback(payload.previous, payload.current));
+    }
+
+    /**
+     * Listens for events from the local API socket whose URI starts with a prefix.
+     *
+     * @param topic The URI prefix, e.g. "/chat/v4/presences".
+     * @param callback Called with each matching event.
+     * @return A function which stops listening and unsubscribes.
+     */
+    public static async subscribe(
+        topic: string,
+        callback: (event: RiotEvent) => void
+    ): Promise<() => Promise<void>> {
+        const channel = await invoke("ws_subscribe", { topic }) as string;
+        const unlisten = await listen<RiotEvent>(channel, ({ payload }) => callback(payload));
+
+        return async () => {
+            unlisten();
+            await invoke("ws_unsubscribe", { topic });
+        };
+    }
+
     /// </editor-fold>
 }
 
@@ -321,6 +336,68 @@ export type APIInfo = {
     protocol: string;
 };
 
+export type LockfileInfo = {
+    name: string;
+    pid: number;
+    port: number;
+    password: string;
+    protocol: string;
+};
+
+export type ConnectionState =
+    | { state: "disconnected"; retryInMs: number }
+    | { state: "connecting"; attempt: number }
+    | { state: "connected" };
+
+export type RiotEvent = {
+    /** The local API path of the resource. */
+    uri: string;
+    eventType: "Create" | "Update" | "Delete";
+    data: unknown;
+};
+
+export type Presence = {
+    /** Player UUID */
+    puuid: string;
+    sessionLoopState: "MENUS" | "PREGAME" | "INGAME" | string | null;
+    partyId: string | null;
+    partySize: number | null;
+    queueId: string | null;
+    /** Map asset path, e.g. "/Game/Maps/Ascent/Ascent" */
+    matchMap: string | null;
+    score: { ally: number; enemy: number } | null;
+    competitiveTier: number | null;
+};
+
+export type PresenceChanged = {
+    puuid: string;
+    /** Names of the changed fields, or "online" if the player appeared or went offline. */
+    changed: (keyof Presence | "online")[];
+    previous: Presence | null;
+    current: Presence | null;
+};
+
+export type GamePhase =
+    | { phase: "unknown" }
+    | { phase: "menus" }
+    | { phase: "pregame"; match_id: string | null }
+    | { phase: "in_game"; match_id: string | null }
+    | { phase: "post_game"; match_id: string | null };
+
+export type GameLogInfo = {
+    region: string | null;
+    shard: string | null;
+    version: string | null;
+    pregame_id: string | null;
+    match_id: string | null;
+};
+
+export type GameLogEvent =
+    | { type: "region"; region: string; shard: string }
+    | { type: "version"; version: string }
+    | { type: "pregame"; match_id: string }
+    | { type: "core_game"; match_id: string };
+
 export type EntitlementsTokenResponse = {
     /** Used as the token in requests */
     accessToken: string;
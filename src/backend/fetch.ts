@@ -1,18 +1,91 @@
 import { invoke } from "@tauri-apps/api";
 
+/**
+ * "utf8" and "base64" bodies are strings; "bytes" bodies are byte arrays.
+ */
+export type BodyEncoding = "utf8" | "base64" | "bytes";
+
 export type RequestOptions = {
     method: "GET" | "POST" | "PUT" | "DELETE";
     headers?: Record<string, string>;
-    body?: string;
+    body?: string | Uint8Array | number[];
+    /** How a string body is encoded. Defaults to "utf8". */
+    bodyEncoding?: BodyEncoding;
+    /** How the response body should be encoded. Defaults to "utf8". */
+    responseEncoding?: BodyEncoding;
+    /** "json" parses the response body natively. Defaults to "text". */
+    responseType?: "text" | "json";
+    /** Time allowed for the whole request, in milliseconds. */
+    timeoutMs?: number;
+    /**
+     * Time allowed to establish the connection, in milliseconds.
+     * Rounded up to 0.25, 0.5, 1, 2, 5, 10 or 30 seconds; longer times are not limited.
+     */
+    connectTimeoutMs?: number;
+    /**
+     * Identifies the request for {@link cancelFetch}.
+     * Rejected with "duplicate_request" while another request with this ID is in flight.
+     */
+    requestId?: string;
 };
 
 export type HttpResponse = {
     success: boolean;
     status: number;
-    body: string;
-    headers: Record<string, string>;
+    /**
+     * A string for "utf8" and "base64", a byte array for "bytes",
+     * or the parsed value when the response type is "json".
+     */
+    body: string | number[] | unknown;
+    encoding: BodyEncoding;
+    /** Every header value, in the order they were received. */
+    headers: HttpHeader[];
 };
 
+export type HttpHeader = {
+    name: string;
+    /** The value, if it is valid UTF-8. */
+    value: string | null;
+    /** The raw value, if it is not valid UTF-8. */
+    raw?: number[];
+};
+
+export type HttpErrorKind =
+    | "client"
+    | "invalid_url"
+    | "invalid_method"
+    | "invalid_header"
+    | "invalid_body"
+    | "connect"
+    | "tls"
+    | "timeout"
+    | "redirect"
+    | "decode"
+    | "json"
+    | "cancelled"
+    | "duplicate_request"
+    | "forbidden"
+    | "request";
+
+/**
+ * Thrown when the native HTTP client fails to complete a request.
+ */
+export class HttpError extends Error {
+    constructor(
+        public readonly kind: HttpErrorKind,
+        message: string
+    ) {
+        super(message);
+    }
+
+    /**
+     * Whether the request may succeed if it is sent again.
+     */
+    public get retryable(): boolean {
+        return this.kind == "connect" || this.kind == "timeout" || this.kind == "request";
+    }
+}
+
 /**
  * Fetches a URL and returns the response.
  *
@@ -23,14 +96,14 @@ export default async function fetch(
     url: string,
     options?: RequestOptions
 ): Promise<HttpResponse> {
-    const response = await invoke("fetch", {
-        request: {
-            url,
-            headers: options?.headers,
-            method: options?.method ?? "GET",
-            body: options?.body
-        }
-    }) as HttpResponse;
+    let response: HttpResponse;
+    try {
+        response = await invoke("fetch", {
+            request: toRequest(url, options)
+        }) as HttpResponse;
+    } catch (error) {
+        throw toHttpError(error);
+    }
 
     if (!response.success) {
         throw new Error("Unable to complete HTTP request.");
@@ -38,3 +111,212 @@ export default async function fetch(
 
     return response;
 }
+
+/**
+ * A request in a {@link fetchMany} batch.
+ */
+export type BatchRequest = RequestOptions & { url: string };
+
+/**
+ * Emitted as the "http-progress" event when a request in a batch completes.
+ */
+export type FetchProgress = {
+    batchId: string | null;
+    index: number;
+    success: boolean;
+    completed: number;
+    total: number;
+};
+
+/**
+ * Fetches several URLs, a few at a time.
+ * Results are returned in the order of the requests;
+ * a failed request is returned as an {@link HttpError} instead of rejecting.
+ *
+ * @param requests The requests to send.
+ * @param concurrency How many requests may be in flight at once.
+ * @param batchId Included in the "http-progress" events for this batch.
+ */
+export async function fetchMany(
+    requests: BatchRequest[],
+    concurrency?: number,
+    batchId?: string
+): Promise<(HttpResponse | HttpError)[]> {
+    const results = await invoke("fetch_many", {
+        requests: requests.map(request => toRequest(request.url, request)),
+        concurrency, batchId
+    }) as ({ ok: HttpResponse } | { err: unknown })[];
+
+    return results.map(result => "ok" in result ? result.ok : toHttpError(result.err));
+}
+
+/**
+ * Converts request options into the shape the backend expects.
+ */
+function toRequest(url: string, options?: RequestOptions) {
+    return {
+        url,
+        headers: options?.headers,
+        method: options?.method ?? "GET",
+        body: options?.body instanceof Uint8Array ?
+            Array.from(options.body) : options?.body,
+        body_encoding: options?.bodyEncoding,
+        response_encoding: options?.responseEncoding,
+        response_type: options?.responseType,
+        timeout_ms: options?.timeoutMs,
+        connect_timeout_ms: options?.connectTimeoutMs,
+        request_id: options?.requestId
+    };
+}
+
+/**
+ * Converts an error returned by the backend into an {@link HttpError}.
+ * Tauri rejects with a plain string when the command arguments are invalid.
+ */
+function toHttpError(error: unknown): HttpError {
+    if (error instanceof HttpError) {
+        return error;
+    }
+    if (typeof error != "object" || error == null || !("kind" in error)) {
+        return new HttpError("request", String(error));
+    }
+
+    const { kind, message } = error as { kind: HttpErrorKind; message?: string | { message: string } };
+    if (typeof message == "string") {
+        return new HttpError(kind, message);
+    }
+    return new HttpError(kind, message?.message ?? String(kind));
+}
+
+/**
+ * Cancels an in-flight request.
+ * The cancelled request rejects with a "cancelled" {@link HttpError}.
+ *
+ * @param requestId The ID passed in the request options.
+ * @return Whether a request was cancelled.
+ */
+export async function cancelFetch(requestId: string): Promise<boolean> {
+    return await invoke("cancel_fetch", { requestId }) as boolean;
+}
+
+/**
+ * Returns the raw bytes of a response body.
+ *
+ * @param response The response to read.
+ */
+export function responseBytes(response: HttpResponse): Uint8Array {
+    if (Array.isArray(response.body)) {
+        return Uint8Array.from(response.body);
+    }
+    if (typeof response.body != "string") {
+        return new TextEncoder().encode(JSON.stringify(response.body));
+    }
+    if (response.encoding == "base64") {
+        return Uint8Array.from(atob(response.body), char => char.charCodeAt(0));
+    }
+
+    return new TextEncoder().encode(response.body);
+}
+
+/**
+ * Returns every UTF-8 value of a response header.
+ *
+ * @param response The response to read.
+ * @param name The header name. Case-insensitive.
+ */
+export function headerValues(response: HttpResponse, name: string): string[] {
+    return response.headers
+        .filter(header => header.name.toLowerCase() == name.toLowerCase() && header.value != null)
+        .map(header => header.value!);
+}
+
+/**
+ * The number of requests waiting on a rate-limited host.
+ * Also emitted as the "http-queue" event whenever it changes.
+ */
+export type QueueDepth = {
+    host: string;
+    depth: number;
+};
+
+/**
+ * Returns the queue depth of every rate-limited host.
+ */
+export async function httpQueue(): Promise<QueueDepth[]> {
+    return await invoke("http_queue") as QueueDepth[];
+}
+
+/**
+ * A response stored in the on-disk HTTP cache.
+ * Times are Unix timestamps in seconds.
+ */
+export type CacheEntry = {
+    method: string;
+    url: string;
+    storedAt: number;
+    expiresAt: number | null;
+    size: number;
+};
+
+/**
+ * Lists every response in the HTTP cache.
+ */
+export async function httpCache(): Promise<CacheEntry[]> {
+    return await invoke("http_cache") as CacheEntry[];
+}
+
+/**
+ * Removes every response from the HTTP cache.
+ * Returns the number of responses removed.
+ */
+export async function clearHttpCache(): Promise<number> {
+    return await invoke("http_cache_clear") as number;
+}
+
+/**
+ * Starts recording every request to a HAR file in the app log directory.
+ * Credentials and the lockfile password are redacted.
+ * Returns the path of the recording.
+ */
+export async function startRecording(): Promise<string> {
+    return await invoke("har_start") as string;
+}
+
+/**
+ * Stops recording requests and writes the recording.
+ * Returns the path of the recording, or null if none was in progress.
+ */
+export async function stopRecording(): Promise<string | null> {
+    return await invoke("har_stop") as string | null;
+}
+
+/**
+ * An HTTP or SOCKS5 proxy for outbound connections.
+ */
+export type ProxyConfig = {
+    /** "http://", "socks5://" or "socks5h://", with optional credentials. */
+    url: string;
+    /** Hosts which are connected to directly. 127.0.0.1 is always bypassed. */
+    bypass?: string[];
+};
+
+/**
+ * Returns the proxy for outbound connections, if any.
+ */
+export async function getProxy(): Promise<ProxyConfig | null> {
+    return await invoke("proxy") as ProxyConfig | null;
+}
+
+/**
+ * Sets the proxy for outbound connections.
+ * The local Riot Client, including its websocket, is always connected to directly.
+ *
+ * @param proxy The proxy, or null to connect directly.
+ */
+export async function setProxy(proxy: ProxyConfig | null): Promise<void> {
+    try {
+        await invoke("set_proxy", { proxy });
+    } catch (error) {
+        throw toHttpError(error);
+    }
+}
@@ -8,6 +8,7 @@ import App from "@ui/App.tsx";
 import API from "@backend/api.ts";
 
 await API.setupClient();
+await API.watchClient();
 
 export const router = createBrowserRouter(
     [ { path: "*", element: <App /> } ]
@@ -19,7 +19,7 @@ function Home() {
             <button
                 className={"bg-blue-200 p-2 w-fit h-fit"}
                 onClick={async () => {
-                    console.log(API.socket);
+                    console.log(API.socketConnected);
                 }}
             >
                 websocket conn?